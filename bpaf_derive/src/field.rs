@@ -9,7 +9,7 @@ use syn::{
 
 use crate::kw;
 use crate::top::split_help_and;
-use crate::utils::{to_kebab_case, LineIter};
+use crate::utils::{snake_case_ident, split_help, to_kebab_case};
 
 #[derive(Debug)]
 pub struct ConstrName {
@@ -22,20 +22,41 @@ pub struct ReqFlag {
     value: ConstrName,
     naming: Vec<StrictNameAttr>,
     help: Option<String>,
+    long_help: Option<String>,
 }
 
 impl ReqFlag {
     pub fn new(value: ConstrName, names: Vec<OptNameAttr>, help: &[String]) -> Self {
         let naming = restrict_names(&value.constr, names);
-        let help = LineIter::from(help).next();
+        let (help, long_help) = split_help(help);
         Self {
             value,
             naming,
             help,
+            long_help,
         }
     }
 }
 
+/// Name to use when turning a value back into arguments, long names are preferred
+fn arg_name(naming: &[StrictNameAttr]) -> Option<String> {
+    naming
+        .iter()
+        .find(|n| matches!(n, StrictNameAttr::Long(_)))
+        .or_else(|| naming.first())
+        .map(|n| match n {
+            StrictNameAttr::Short(s) => format!("-{}", s.value()),
+            StrictNameAttr::Long(l) => format!("--{}", l.value()),
+        })
+}
+
+impl ReqFlag {
+    /// Pattern matching the value and a name to use as an argument
+    pub fn unparse(&self) -> (&ConstrName, String) {
+        (&self.value, arg_name(&self.naming).unwrap_or_default())
+    }
+}
+
 impl ToTokens for ReqFlag {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         let mut first = true;
@@ -48,11 +69,14 @@ impl ToTokens for ReqFlag {
             naming.to_tokens(tokens);
             first = false;
         }
-        if let Some(help) = &self.help {
-            // help only makes sense for named things
-            if !first {
+        // help only makes sense for named things
+        if !first {
+            if let Some(help) = &self.help {
                 quote!(.help(#help)).to_tokens(tokens);
             }
+            if let Some(long_help) = &self.long_help {
+                quote!(.long_help(#long_help)).to_tokens(tokens);
+            }
         }
         let value = &self.value;
         quote!(.req_flag(#value)).to_tokens(tokens);
@@ -68,6 +92,18 @@ pub struct FieldAttrs<T> {
     consumer: Option<ConsumerAttr>,
     postpr: Vec<PostprAttr>,
     help: Option<String>,
+    long_help: Option<String>,
+    shape: FieldShape,
+    /// type of a single value of an `external` or `flatten` field, used by `unparse`
+    external_ty: Option<Type>,
+}
+
+/// How many values a field holds, used to turn the field back into arguments
+#[derive(Debug, Clone, Copy)]
+enum FieldShape {
+    Direct,
+    Optional,
+    Multiple,
 }
 
 impl<T> Default for FieldAttrs<T> {
@@ -79,6 +115,9 @@ impl<T> Default for FieldAttrs<T> {
             consumer: None,
             postpr: Vec::new(),
             help: None,
+            long_help: None,
+            shape: FieldShape::Direct,
+            external_ty: None,
         }
     }
 }
@@ -112,7 +151,9 @@ enum PostprAttr {
     Optional,
     Parse(Ident),
     Fallback(Box<Expr>),
+    FallbackDisplay(Box<Expr>),
     FallbackWith(Box<Expr>),
+    Values(Ident),
     Tokens(TokenStream),
 }
 
@@ -125,7 +166,11 @@ impl PostprAttr {
             | PostprAttr::Tokens(_)
             | PostprAttr::Optional
             | PostprAttr::Parse(_) => false,
-            PostprAttr::Guard(_, _) | PostprAttr::Fallback(_) | PostprAttr::FallbackWith(_) => true,
+            PostprAttr::Guard(_, _)
+            | PostprAttr::Fallback(_)
+            | PostprAttr::FallbackDisplay(_)
+            | PostprAttr::FallbackWith(_)
+            | PostprAttr::Values(_) => true,
         }
     }
 }
@@ -179,16 +224,22 @@ impl<T: Parse> Parse for FieldAttrs<T> {
             naming,
             consumer,
             postpr,
-            // those two are filled in during postprocessing
+            // those are filled in during postprocessing
             name: None,
             help: None,
+            long_help: None,
+            shape: FieldShape::Direct,
+            external_ty: None,
         })
     }
 }
 
 #[derive(Debug, Clone)]
-struct ExtAttr {
-    ident: Option<Ident>,
+enum ExtAttr {
+    /// `external`, `external(fun)` or `external(fun(args))`
+    External(Option<Box<Expr>>),
+    /// `flatten` or `flatten("prefix")`, function name comes from the field type
+    Flatten(Option<LitStr>, Option<Ident>),
 }
 
 impl Parse for ExtAttr {
@@ -198,11 +249,22 @@ impl Parse for ExtAttr {
             if input.peek(token::Paren) {
                 let content;
                 let _ = parenthesized!(content in input);
-                Ok(Self {
-                    ident: Some(content.parse::<Ident>()?),
-                })
+                let expr = content.parse::<Expr>()?;
+                match expr {
+                    Expr::Path(_) | Expr::Call(_) => Ok(Self::External(Some(Box::new(expr)))),
+                    _ => Err(content.error("Expected a function name or a function call")),
+                }
+            } else {
+                Ok(Self::External(None))
+            }
+        } else if input.peek(kw::flatten) {
+            input.parse::<kw::flatten>()?;
+            if input.peek(token::Paren) {
+                let content;
+                let _ = parenthesized!(content in input);
+                Ok(Self::Flatten(Some(content.parse::<LitStr>()?), None))
             } else {
-                Ok(Self { ident: None })
+                Ok(Self::Flatten(None, None))
             }
         } else {
             Err(input.error("Not a name attribute"))
@@ -300,6 +362,11 @@ impl Parse for PostprAttr {
             let _ = parenthesized!(content in input);
             let expr = content.parse::<Expr>()?;
             Ok(Self::Fallback(Box::new(expr)))
+        } else if input.peek(kw::fallback_display) {
+            input.parse::<kw::fallback_display>()?;
+            let _ = parenthesized!(content in input);
+            let expr = content.parse::<Expr>()?;
+            Ok(Self::FallbackDisplay(Box::new(expr)))
         } else if input.peek(kw::fallback_with) {
             input.parse::<kw::fallback_with>()?;
             let _ = parenthesized!(content in input);
@@ -315,6 +382,11 @@ impl Parse for PostprAttr {
             let _ = parenthesized!(content in input);
             let map_fn = content.parse::<Ident>()?;
             Ok(Self::Map(map_fn))
+        } else if input.peek(kw::values) {
+            input.parse::<kw::values>()?;
+            let _ = parenthesized!(content in input);
+            let values_fn = content.parse::<Ident>()?;
+            Ok(Self::Values(values_fn))
         } else if input.peek(kw::from_str) {
             input.parse::<kw::from_str>()?;
             let _ = parenthesized!(content in input);
@@ -426,15 +498,13 @@ impl FieldParser {
                 "This consumer needs a name, you can specify it with long(\"name\") or short('n')",
             ));
         }
-        if let Some(ext) = &parser.external {
-            if ext.ident.is_none() {
-                return Err(
-                    i.error("Name shortcut for external attribute is only valid for named field")
-                );
-            }
+        if let Some(ExtAttr::External(None)) = &parser.external {
+            return Err(
+                i.error("Name shortcut for external attribute is only valid for named field")
+            );
         }
 
-        parser.help = LineIter::from(&help[..]).next();
+        (parser.help, parser.long_help) = split_help(&help);
         Ok(parser)
     }
 
@@ -461,7 +531,7 @@ impl FieldParser {
             return Err(i.error(err));
         }
 
-        parser.help = LineIter::from(&help[..]).next();
+        (parser.help, parser.long_help) = split_help(&help);
 
         Ok(parser)
     }
@@ -475,6 +545,9 @@ impl FieldAttrs<OptNameAttr> {
             consumer: self.consumer,
             postpr: self.postpr,
             help: self.help,
+            long_help: self.long_help,
+            shape: self.shape,
+            external_ty: self.external_ty,
             name: Some(name),
         }
     }
@@ -521,6 +594,23 @@ fn restrict_names(base_name: &Ident, attrs: Vec<OptNameAttr>) -> Vec<StrictNameA
 
 impl FieldAttrs<StrictNameAttr> {
     fn implicit_consumer(&mut self, ty: &Type) -> Option<&'static str> {
+        if let Some(ExtAttr::Flatten(_, fun @ None)) = &mut self.external {
+            let inner = match split_type(ty) {
+                Shape::Direct(ty) => ty,
+                Shape::Optional(ty) => {
+                    if self.postpr.iter().all(PostprAttr::can_derive) {
+                        self.postpr.insert(0, PostprAttr::Optional);
+                    }
+                    self.shape = FieldShape::Optional;
+                    ty
+                }
+                Shape::Bool | Shape::Multiple(_) => return Some("Can't flatten this type"),
+            };
+            match inner {
+                Type::Path(p) => *fun = p.path.segments.last().map(|s| snake_case_ident(&s.ident)),
+                _ => return Some("Can't derive a parser name for this type, try using `external`"),
+            }
+        }
         let arg = LitStr::new("ARG", ty.span());
         let shape = split_type(ty);
         let can_derive_postpr =
@@ -541,16 +631,22 @@ impl FieldAttrs<StrictNameAttr> {
                 if can_derive_postpr {
                     self.postpr.insert(0, PostprAttr::Optional);
                 }
+                self.shape = FieldShape::Optional;
                 ty
             }
             Shape::Multiple(ty) => {
                 if can_derive_postpr {
                     self.postpr.insert(0, PostprAttr::Many(None));
                 }
+                self.shape = FieldShape::Multiple;
                 ty
             }
         };
 
+        if self.external.is_some() {
+            self.external_ty = Some(inner_ty.clone());
+        }
+
         if self.consumer.is_none() && self.external.is_none() {
             if !can_derive_postpr {
                 return Some(
@@ -566,7 +662,15 @@ impl FieldAttrs<StrictNameAttr> {
         }
 
         if can_derive_postpr && self.external.is_none() {
-            if os_str {
+            // values replace from_str and must come before any shape related postprocessing
+            if let Some(ix) = self
+                .postpr
+                .iter()
+                .position(|p| matches!(p, PostprAttr::Values(_)))
+            {
+                let attr = self.postpr.remove(ix);
+                self.postpr.insert(0, attr);
+            } else if os_str {
                 let attr = PostprAttr::Tokens(quote!(map(#inner_ty::from)));
                 self.postpr.insert(0, attr);
             } else if inner_ty != parse_quote!(String) {
@@ -578,6 +682,78 @@ impl FieldAttrs<StrictNameAttr> {
         None
     }
 }
+impl FieldParser {
+    /// Code that adds items for a field bound to `var` to `args`
+    pub fn unparse(&self, var: &Ident) -> Result<TokenStream> {
+        let fail = |msg| Err(syn::Error::new(var.span(), msg));
+        if self
+            .postpr
+            .iter()
+            .any(|p| matches!(p, PostprAttr::Map(_) | PostprAttr::Parse(_)))
+        {
+            return fail("Can't unparse a field with `map` or `parse`");
+        }
+        let os = quote!(::std::ffi::OsString);
+        let name = arg_name(&self.naming);
+        let value = match self.postpr.iter().find_map(|p| match p {
+            PostprAttr::Values(f) => Some(f),
+            _ => None,
+        }) {
+            Some(f) => quote!(#os::from(#f().name_of(v).unwrap_or_default())),
+            None => quote!(#os::from(::std::string::ToString::to_string(v))),
+        };
+        let os_value = quote!(#os::from(::std::convert::AsRef::<::std::ffi::OsStr>::as_ref(v)));
+        let body = match (&self.external, &self.consumer) {
+            (Some(ExtAttr::Flatten(Some(_), _)), _) => {
+                return fail("Can't unparse a field flattened with a prefix")
+            }
+            (Some(_), _) => quote!(::bpaf::Unparse::unparse(v, args);),
+            (None, Some(ConsumerAttr::Switch)) => quote!(if *v { args.flag(#name); }),
+            (None, Some(ConsumerAttr::Arg(_))) => quote!(args.named(#name, #value);),
+            (None, Some(ConsumerAttr::ArgOs(_))) => quote!(args.named(#name, #os_value);),
+            (None, Some(ConsumerAttr::Pos(_))) => quote!(args.positional(#value);),
+            (None, Some(ConsumerAttr::PosOs(_))) => quote!(args.positional(#os_value);),
+            (None, None) => return fail("Can't unparse a field without a consumer"),
+        };
+        Ok(match self.shape {
+            FieldShape::Direct => quote!({ let v = #var; #body }),
+            FieldShape::Optional => quote!(if let Some(v) = #var { #body }),
+            FieldShape::Multiple => quote!(for v in #var { #body }),
+        })
+    }
+
+    /// Whether the field adds positional and named items when unparsed, as constant
+    /// expressions. `external` and `flatten` fields take them from `Unparse` of their type
+    pub fn unparse_kinds(&self) -> (TokenStream, TokenStream) {
+        match (&self.external_ty, &self.consumer) {
+            (Some(ty), _) => (
+                quote!(<#ty as ::bpaf::Unparse>::POSITIONAL),
+                quote!(<#ty as ::bpaf::Unparse>::NAMED),
+            ),
+            (None, Some(ConsumerAttr::Pos(_) | ConsumerAttr::PosOs(_))) => {
+                (quote!(true), quote!(false))
+            }
+            (None, _) => (quote!(false), quote!(true)),
+        }
+    }
+
+    /// Field consumes positional items directly, `None` for `external` and `flatten` fields
+    pub fn is_positional(&self) -> Option<bool> {
+        match (&self.external, &self.consumer) {
+            (Some(_), _) => None,
+            (None, consumer) => Some(matches!(
+                consumer,
+                Some(ConsumerAttr::Pos(_) | ConsumerAttr::PosOs(_))
+            )),
+        }
+    }
+
+    /// Name of the field for error messages
+    pub const fn name(&self) -> Option<&Ident> {
+        self.name.as_ref()
+    }
+}
+
 impl<T> FieldAttrs<T> {
     fn consumer_needs_name(&self) -> Option<bool> {
         Some(match self.consumer.as_ref()? {
@@ -591,8 +767,24 @@ impl ToTokens for FieldAttrs<StrictNameAttr> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         let mut first = true;
         if let Some(ext) = &self.external {
-            let name = ext.ident.as_ref().or(self.name.as_ref()).unwrap();
-            quote!(#name()).to_tokens(tokens);
+            match ext {
+                ExtAttr::External(Some(expr)) => match expr.as_ref() {
+                    Expr::Path(fun) => quote!(#fun()),
+                    call => quote!(#call),
+                },
+                ExtAttr::External(None) => {
+                    let name = self.name.as_ref().unwrap();
+                    quote!(#name())
+                }
+                ExtAttr::Flatten(prefix, fun) => {
+                    let fun = fun.as_ref().unwrap();
+                    match prefix {
+                        Some(prefix) => quote!(#fun().long_prefix(#prefix)),
+                        None => quote!(#fun()),
+                    }
+                }
+            }
+            .to_tokens(tokens);
         } else {
             if first {
                 quote!(::bpaf::).to_tokens(tokens);
@@ -605,17 +797,32 @@ impl ToTokens for FieldAttrs<StrictNameAttr> {
                 naming.to_tokens(tokens);
                 first = false;
             }
-            if let Some(help) = &self.help {
-                // help only makes sense for named things
-                if !first {
-                    quote!(.help(#help)).to_tokens(tokens);
+            let long_help = self
+                .long_help
+                .as_ref()
+                .map(|long_help| quote!(.long_help(#long_help)));
+            match (&self.consumer, &self.help) {
+                // positional items with help go through a builder
+                (Some(ConsumerAttr::Pos(arg)), Some(help)) if first => {
+                    quote!(Positional::new(#arg).help(#help) #long_help .string())
+                        .to_tokens(tokens);
                 }
-            }
-            if let Some(cons) = &self.consumer {
-                if !first {
-                    quote!(.).to_tokens(tokens);
+                (Some(ConsumerAttr::PosOs(arg)), Some(help)) if first => {
+                    quote!(Positional::new(#arg).help(#help) #long_help .os()).to_tokens(tokens);
+                }
+                (cons, help) => {
+                    if let Some(help) = help {
+                        if !first {
+                            quote!(.help(#help) #long_help).to_tokens(tokens);
+                        }
+                    }
+                    if let Some(cons) = cons {
+                        if !first {
+                            quote!(.).to_tokens(tokens);
+                        }
+                        cons.to_tokens(tokens);
+                    }
                 }
-                cons.to_tokens(tokens);
             }
         }
         for postpr in &self.postpr {
@@ -636,7 +843,9 @@ impl ToTokens for PostprAttr {
             PostprAttr::Optional => quote!(optional()),
             PostprAttr::Parse(f) => quote!(parse(#f)),
             PostprAttr::Fallback(v) => quote!(fallback(#v)),
+            PostprAttr::FallbackDisplay(v) => quote!(fallback_display(#v)),
             PostprAttr::FallbackWith(v) => quote!(fallback_with(#v)),
+            PostprAttr::Values(f) => quote!(values(#f())),
             PostprAttr::Tokens(t) => quote!(#t),
         }
         .to_tokens(tokens);
@@ -749,6 +958,18 @@ mod tests {
         assert_eq!(input.to_token_stream().to_string(), output.to_string());
     }
 
+    #[test]
+    fn derive_fallback_display() {
+        let input: NamedField = parse_quote! {
+            #[bpaf(fallback_display(Level::Info))]
+            level: Level
+        };
+        let output = quote! {
+            ::bpaf::long("level").argument("ARG").from_str::<Level>().fallback_display(Level::Info)
+        };
+        assert_eq!(input.to_token_stream().to_string(), output.to_string());
+    }
+
     #[test]
     fn derive_fallback_with() {
         let input: NamedField = parse_quote! {
@@ -799,6 +1020,90 @@ mod tests {
         assert_eq!(input.to_token_stream().to_string(), output.to_string());
     }
 
+    #[test]
+    fn derive_external_call() {
+        let input: NamedField = parse_quote! {
+            #[bpaf(external(net::port("listen", 8080)))]
+            listen: u16
+        };
+        let output = quote! {
+            net::port("listen", 8080)
+        };
+        assert_eq!(input.to_token_stream().to_string(), output.to_string());
+    }
+
+    #[test]
+    fn derive_external_path() {
+        let input: UnnamedField = parse_quote! {
+            #[bpaf(external(net::port))]
+            u16
+        };
+        let output = quote! {
+            net::port()
+        };
+        assert_eq!(input.to_token_stream().to_string(), output.to_string());
+    }
+
+    #[test]
+    fn derive_flatten() {
+        let input: NamedField = parse_quote! {
+            #[bpaf(flatten)]
+            db: config::DbOpts
+        };
+        let output = quote! {
+            db_opts()
+        };
+        assert_eq!(input.to_token_stream().to_string(), output.to_string());
+    }
+
+    #[test]
+    fn derive_flatten_prefix() {
+        let input: NamedField = parse_quote! {
+            #[bpaf(flatten("db"))]
+            db: Option<DbOpts>
+        };
+        let output = quote! {
+            db_opts().long_prefix("db").optional()
+        };
+        assert_eq!(input.to_token_stream().to_string(), output.to_string());
+    }
+
+    #[test]
+    fn derive_values() {
+        let input: NamedField = parse_quote! {
+            #[bpaf(values(format))]
+            format: Option<Format>
+        };
+        let output = quote! {
+            ::bpaf::long("format").argument("ARG").values(format()).optional()
+        };
+        assert_eq!(input.to_token_stream().to_string(), output.to_string());
+    }
+
+    #[test]
+    fn derive_values_explicit() {
+        let input: NamedField = parse_quote! {
+            #[bpaf(short, argument("FMT"), values(format), fallback(Format::Text))]
+            format: Format
+        };
+        let output = quote! {
+            ::bpaf::short('f').argument("FMT").values(format()).fallback(Format::Text)
+        };
+        assert_eq!(input.to_token_stream().to_string(), output.to_string());
+    }
+
+    #[test]
+    fn positional_with_help() {
+        let input: UnnamedField = parse_quote! {
+            /// File to process
+            PathBuf
+        };
+        let output = quote! {
+            ::bpaf::Positional::new("ARG").help("File to process").os().map(PathBuf::from)
+        };
+        assert_eq!(input.to_token_stream().to_string(), output.to_string());
+    }
+
     #[test]
     fn derive_field_guard() {
         let input: NamedField = parse_quote! {
@@ -823,7 +1128,26 @@ mod tests {
             pub(crate) flag: bool
         };
         let output = quote! {
-            ::bpaf::long("flag").help("multi\nvis").switch()
+            ::bpaf::long("flag").help("multi\nvis").long_help("multi\nvis\n\nhidden").switch()
+        };
+        assert_eq!(input.to_token_stream().to_string(), output.to_string());
+    }
+
+    #[test]
+    fn positional_with_long_help() {
+        let input: UnnamedField = parse_quote! {
+            /// File to process
+            ///
+            ///
+            /// Use - for stdin
+            #[bpaf(positional("FILE"))]
+            String
+        };
+        let output = quote! {
+            ::bpaf::Positional::new("FILE")
+                .help("File to process")
+                .long_help("File to process\n\nUse - for stdin")
+                .string()
         };
         assert_eq!(input.to_token_stream().to_string(), output.to_string());
     }
@@ -4,6 +4,14 @@ custom_keyword!(construct);
 custom_keyword!(generate);
 custom_keyword!(options);
 custom_keyword!(command);
+custom_keyword!(values);
+custom_keyword!(unparse);
+custom_keyword!(ignore_case);
+custom_keyword!(rename);
+custom_keyword!(alias);
+custom_keyword!(hidden_alias);
+custom_keyword!(allow_prefix);
+custom_keyword!(default_command);
 
 custom_keyword!(short);
 custom_keyword!(long);
@@ -14,10 +22,12 @@ custom_keyword!(argument_os);
 custom_keyword!(positional_os);
 custom_keyword!(switch);
 custom_keyword!(external);
+custom_keyword!(flatten);
 
 custom_keyword!(from_str);
 custom_keyword!(fallback);
 custom_keyword!(fallback_with);
+custom_keyword!(fallback_display);
 custom_keyword!(guard);
 custom_keyword!(many);
 custom_keyword!(some);
@@ -9,7 +9,7 @@ use syn::{
 
 use crate::field::{ConstrName, Doc, FieldParser, OptNameAttr, ReqFlag};
 use crate::kw;
-use crate::utils::{snake_case_ident, to_snake_case, LineIter};
+use crate::utils::{snake_case_ident, to_kebab_case, to_snake_case, LineIter};
 
 #[derive(Debug)]
 pub struct Top {
@@ -25,6 +25,9 @@ pub struct Top {
     outer_ty: Ident,
 
     kind: ParserKind,
+
+    /// `Unparse` implementation, if requested
+    unparse: Option<TokenStream>,
 }
 
 #[derive(Debug)]
@@ -32,17 +35,27 @@ pub struct Top {
 enum ParserKind {
     BParser(BParser),
     OParser(OParser),
+    Values(Vec<EnumValue>, bool),
 }
 
 #[derive(Debug)]
 enum BParser {
-    Command(LitStr, Box<OParser>),
+    Command(LitStr, CommandExtra, Box<OParser>),
     CargoHelper(LitStr, Box<BParser>),
     Constructor(ConstrName, Fields),
     Singleton(ReqFlag),
     Fold(Vec<BParser>),
 }
 
+/// A single variant of a fieldless enum used as a value
+#[derive(Debug)]
+struct EnumValue {
+    value: ConstrName,
+    name: LitStr,
+    aliases: Vec<LitStr>,
+    help: Option<String>,
+}
+
 #[derive(Debug)]
 struct OParser {
     inner: Box<BParser>,
@@ -78,6 +91,7 @@ impl ToTokens for Decor {
 /// A collection of fields, corresponds to a single constructor in enum or the whole struct but
 /// without the name
 #[derive(Clone, Debug)]
+#[allow(clippy::enum_variant_names)]
 enum Fields {
     Named(Punctuated<FieldParser, Token![,]>),
     Unnamed(Punctuated<FieldParser, Token![,]>),
@@ -106,6 +120,7 @@ enum OuterKind {
     Construct,
     Options(Option<LitStr>),
     Command(Option<LitStr>),
+    Values(bool),
 }
 
 #[derive(Clone, Debug)]
@@ -114,25 +129,28 @@ enum OuterAttr {
     Construct,
     Generate(Ident),
     Command(Option<LitStr>),
+    /// `values` or `values(ignore_case)`
+    Values(bool),
+    Unparse,
 }
 
 #[derive(Clone, Debug)]
-struct CommandAttr {
-    name: Option<LitStr>,
+enum ValueAttr {
+    Rename(LitStr),
+    Alias(LitStr),
 }
 
-impl Parse for CommandAttr {
+impl Parse for ValueAttr {
     fn parse(input: parse::ParseStream) -> Result<Self> {
-        if input.peek(kw::command) {
-            let _: kw::command = input.parse()?;
-            if input.peek(token::Paren) {
-                let content;
-                let _ = parenthesized!(content in input);
-                let lit = content.parse::<LitStr>()?;
-                Ok(Self { name: Some(lit) })
-            } else {
-                Ok(Self { name: None })
-            }
+        let content;
+        if input.peek(kw::rename) {
+            let _: kw::rename = input.parse()?;
+            let _ = parenthesized!(content in input);
+            Ok(Self::Rename(content.parse()?))
+        } else if input.peek(kw::alias) {
+            let _: kw::alias = input.parse()?;
+            let _ = parenthesized!(content in input);
+            Ok(Self::Alias(content.parse()?))
         } else {
             Err(input.error("Unexpected attribute"))
         }
@@ -170,31 +188,112 @@ impl Parse for OuterAttr {
             } else {
                 Ok(Self::Command(None))
             }
+        } else if input.peek(kw::unparse) {
+            let _: kw::unparse = input.parse()?;
+            Ok(Self::Unparse)
+        } else if input.peek(kw::values) {
+            let _: kw::values = input.parse()?;
+            if input.peek(token::Paren) {
+                let content;
+                let _ = parenthesized!(content in input);
+                let _: kw::ignore_case = content.parse()?;
+                Ok(Self::Values(true))
+            } else {
+                Ok(Self::Values(false))
+            }
         } else {
             Err(input.error("Unexpected attribute"))
         }
     }
 }
 
-struct InnerAttr(Option<LitStr>);
+/// Annotations for enum variants: `command` along with its aliases
+enum InnerAttr {
+    Command(Option<LitStr>),
+    Alias(LitStr),
+    HiddenAlias(LitStr),
+    AllowPrefix,
+    DefaultCommand,
+}
+
 impl Parse for InnerAttr {
     fn parse(input: parse::ParseStream) -> Result<Self> {
+        let content;
         if input.peek(kw::command) {
             let _: kw::command = input.parse()?;
             if input.peek(token::Paren) {
-                let content;
                 let _ = parenthesized!(content in input);
                 let lit = content.parse::<LitStr>()?;
-                Ok(Self(Some(lit)))
+                Ok(Self::Command(Some(lit)))
             } else {
-                Ok(Self(None))
+                Ok(Self::Command(None))
             }
+        } else if input.peek(kw::alias) {
+            let _: kw::alias = input.parse()?;
+            let _ = parenthesized!(content in input);
+            Ok(Self::Alias(content.parse()?))
+        } else if input.peek(kw::hidden_alias) {
+            let _: kw::hidden_alias = input.parse()?;
+            let _ = parenthesized!(content in input);
+            Ok(Self::HiddenAlias(content.parse()?))
+        } else if input.peek(kw::allow_prefix) {
+            let _: kw::allow_prefix = input.parse()?;
+            Ok(Self::AllowPrefix)
+        } else if input.peek(kw::default_command) {
+            let _: kw::default_command = input.parse()?;
+            Ok(Self::DefaultCommand)
         } else {
             Err(input.error("Unexpected attribute"))
         }
     }
 }
 
+/// Aliases, prefix matching and default command marker for a command
+#[derive(Debug, Default)]
+struct CommandExtra {
+    aliases: Vec<LitStr>,
+    hidden_aliases: Vec<LitStr>,
+    allow_prefix: bool,
+    default: bool,
+}
+
+impl CommandExtra {
+    const fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+            && self.hidden_aliases.is_empty()
+            && !self.allow_prefix
+            && !self.default
+    }
+}
+
+/// Split variant annotations into a command name and extras, `None` if there's no `command`
+fn command_attrs(attrs: Vec<InnerAttr>, variant: &Ident) -> Result<Option<(LitStr, CommandExtra)>> {
+    let mut name = None;
+    let mut extra = CommandExtra::default();
+    for attr in attrs {
+        match attr {
+            InnerAttr::Command(n) => {
+                name = Some(n.unwrap_or_else(|| {
+                    let n = to_snake_case(&variant.to_string());
+                    LitStr::new(&n, variant.span())
+                }));
+            }
+            InnerAttr::Alias(a) => extra.aliases.push(a),
+            InnerAttr::HiddenAlias(a) => extra.hidden_aliases.push(a),
+            InnerAttr::AllowPrefix => extra.allow_prefix = true,
+            InnerAttr::DefaultCommand => extra.default = true,
+        }
+    }
+    match name {
+        Some(name) => Ok(Some((name, extra))),
+        None if extra.is_empty() => Ok(None),
+        None => Err(syn::Error::new(
+            variant.span(),
+            "Aliases, prefix matching and default_command are only valid for commands",
+        )),
+    }
+}
+
 pub fn split_help_and<T: Parse>(attrs: &[Attribute]) -> Result<(Vec<String>, Vec<T>)> {
     let mut help = Vec::new();
     let mut res = Vec::new();
@@ -218,6 +317,7 @@ impl Parse for Top {
 
         let outer_ty;
         let mut name = None;
+        let mut unparse = false;
 
         let kind;
 
@@ -230,6 +330,8 @@ impl Parse for Top {
                     OuterAttr::Construct => outer_kind = Some(OuterKind::Construct),
                     OuterAttr::Generate(n) => name = Some(n.clone()),
                     OuterAttr::Command(n) => outer_kind = Some(OuterKind::Command(n)),
+                    OuterAttr::Values(i) => outer_kind = Some(OuterKind::Values(i)),
+                    OuterAttr::Unparse => unparse = true,
                 }
             }
 
@@ -272,9 +374,13 @@ impl Parse for Top {
                         let n = to_snake_case(&outer_ty.to_string());
                         LitStr::new(&n, outer_ty.span())
                     });
-                    let cmd = BParser::Command(cmd_name, Box::new(oparser));
+                    let cmd =
+                        BParser::Command(cmd_name, CommandExtra::default(), Box::new(oparser));
                     kind = ParserKind::BParser(cmd);
                 }
+                OuterKind::Values(_) => {
+                    return Err(input.error("values is only valid for fieldless enums"))
+                }
             }
         } else if input.peek(Token![enum]) {
             let (help, outer) = split_help_and::<OuterAttr>(&attrs)?;
@@ -285,12 +391,15 @@ impl Parse for Top {
                     OuterAttr::Construct => outer_kind = Some(OuterKind::Construct),
                     OuterAttr::Generate(n) => name = Some(n.clone()),
                     OuterAttr::Command(n) => outer_kind = Some(OuterKind::Command(n)),
+                    OuterAttr::Values(i) => outer_kind = Some(OuterKind::Values(i)),
+                    OuterAttr::Unparse => unparse = true,
                 }
             }
 
             let _ = input.parse::<Token![enum]>()?;
             outer_ty = input.parse::<Ident>()?;
             let mut branches: Vec<BParser> = Vec::new();
+            let mut values = Vec::new();
 
             let enum_contents;
             let _ = braced!(enum_contents in input);
@@ -307,42 +416,55 @@ impl Parse for Top {
                     constr: inner_ty.clone(),
                 };
 
-                if enum_contents.peek(token::Paren) || enum_contents.peek(token::Brace) {
+                if let Some(OuterKind::Values(_)) = outer_kind {
+                    if enum_contents.peek(token::Paren) || enum_contents.peek(token::Brace) {
+                        return Err(
+                            enum_contents.error("values can only contain fieldless variants")
+                        );
+                    }
+                    let (help, attrs) = split_help_and::<ValueAttr>(&attrs)?;
+                    let mut name =
+                        LitStr::new(&to_kebab_case(&inner_ty.to_string()), inner_ty.span());
+                    let mut aliases = Vec::new();
+                    for attr in attrs {
+                        match attr {
+                            ValueAttr::Rename(n) => name = n,
+                            ValueAttr::Alias(a) => aliases.push(a),
+                        }
+                    }
+                    values.push(EnumValue {
+                        value: constr,
+                        name,
+                        aliases,
+                        help: LineIter::from(&help[..]).next(),
+                    });
+                } else if enum_contents.peek(token::Paren) || enum_contents.peek(token::Brace) {
                     let (help, inner) = split_help_and::<InnerAttr>(&attrs)?;
 
                     let bra = enum_contents.parse::<Fields>()?;
 
-                    match &inner[..] {
-                        [] => branches.push(BParser::Constructor(constr, bra)),
-                        [InnerAttr(maybe_command_name)] => {
-                            let cmd_name = maybe_command_name.clone().unwrap_or_else(|| {
-                                let n = to_snake_case(&inner_ty.to_string());
-                                LitStr::new(&n, inner_ty.span())
-                            });
+                    match command_attrs(inner, &inner_ty)? {
+                        None => branches.push(BParser::Constructor(constr, bra)),
+                        Some((cmd_name, extra)) => {
                             let decor = Decor::new(&help);
                             let oparser = OParser {
                                 inner: Box::new(BParser::Constructor(constr, bra)),
                                 decor,
                             };
-                            branches.push(BParser::Command(cmd_name, Box::new(oparser)));
+                            branches.push(BParser::Command(cmd_name, extra, Box::new(oparser)));
                         }
-                        _ => todo!("error here, todo"),
                     }
-                } else if let Ok((help, Some(inner))) = split_help_and::<CommandAttr>(&attrs)
-                    .map(|(h, a)| (h, (a.len() == 1).then(|| a.first().cloned()).flatten()))
+                } else if let Ok((help, Some((cmd_name, extra)))) =
+                    split_help_and::<InnerAttr>(&attrs)
+                        .and_then(|(h, a)| Ok((h, command_attrs(a, &inner_ty)?)))
                 {
-                    let cmd_name = inner.name.clone().unwrap_or_else(|| {
-                        let n = to_snake_case(&inner_ty.to_string());
-                        LitStr::new(&n, inner_ty.span())
-                    });
-
                     let decor = Decor::new(&help);
                     let fields = Fields::NoFields;
                     let oparser = OParser {
                         inner: Box::new(BParser::Constructor(constr, fields)),
                         decor,
                     };
-                    branches.push(BParser::Command(cmd_name, Box::new(oparser)));
+                    branches.push(BParser::Command(cmd_name, extra, Box::new(oparser)));
                 } else {
                     let (help, inner) = split_help_and::<OptNameAttr>(&attrs)?;
                     branches.push(BParser::Singleton(ReqFlag::new(constr, inner, &help)));
@@ -380,18 +502,30 @@ impl Parse for Top {
                         inner: Box::new(inner),
                         decor,
                     };
-                    kind = ParserKind::BParser(BParser::Command(cmd_name, Box::new(oparser)));
+                    let cmd =
+                        BParser::Command(cmd_name, CommandExtra::default(), Box::new(oparser));
+                    kind = ParserKind::BParser(cmd);
+                }
+                OuterKind::Values(ignore_case) => {
+                    kind = ParserKind::Values(values, ignore_case);
                 }
             }
         } else {
             return Err(input.error("Only struct and enum types are supported"));
         }
 
+        let unparse = if unparse {
+            Some(kind.unparse(&outer_ty)?)
+        } else {
+            None
+        };
+
         Ok(Top {
             name: name.unwrap_or_else(|| snake_case_ident(&outer_ty)),
             vis,
             outer_ty,
             kind,
+            unparse,
         })
     }
 }
@@ -403,10 +537,12 @@ impl ToTokens for Top {
             vis,
             outer_ty,
             kind,
+            unparse,
         } = self;
         let outer_kind = match kind {
             ParserKind::BParser(_) => quote!(Parser),
             ParserKind::OParser(_) => quote!(OptionParser),
+            ParserKind::Values(..) => quote!(Values),
         };
         quote!(
             #vis fn #name() -> ::bpaf::#outer_kind<#outer_ty> {
@@ -414,6 +550,126 @@ impl ToTokens for Top {
             }
         )
         .to_tokens(tokens);
+        unparse.to_tokens(tokens);
+    }
+}
+
+impl ParserKind {
+    /// `Unparse` implementation for the parsed type
+    fn unparse(&self, outer_ty: &Ident) -> Result<TokenStream> {
+        let arms = match self {
+            ParserKind::BParser(bp) => bp.unparse_arms(&[])?,
+            ParserKind::OParser(op) => op.inner.unparse_arms(&[])?,
+            ParserKind::Values(..) => {
+                return Err(syn::Error::new(
+                    outer_ty.span(),
+                    "unparse is not supported for values, use `Values::name_of` instead",
+                ))
+            }
+        };
+        let positional = any_of(arms.iter().map(|arm| &arm.positional));
+        let named = any_of(arms.iter().map(|arm| &arm.named));
+        let arms = arms.iter().map(|arm| &arm.arm);
+        Ok(quote! {
+            impl ::bpaf::Unparse for #outer_ty {
+                const POSITIONAL: bool = #positional;
+                const NAMED: bool = #named;
+                fn unparse(&self, args: &mut ::bpaf::UnparseArgs) {
+                    match self {
+                        #(#arms)*
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Match arm of `Unparse` along with constant expressions telling if it adds positional and
+/// named items
+struct UnparseArm {
+    arm: TokenStream,
+    positional: TokenStream,
+    named: TokenStream,
+}
+
+/// Constant expression that is true if any of `xs` is, folded if all of them are literals
+fn any_of<'a, I>(xs: I) -> TokenStream
+where
+    I: Iterator<Item = &'a TokenStream>,
+{
+    let xs = xs.map(ToString::to_string).collect::<Vec<_>>();
+    if xs.iter().any(|x| x == "true") {
+        quote!(true)
+    } else {
+        let xs = xs
+            .iter()
+            .filter(|x| *x != "false")
+            .map(|x| x.parse::<TokenStream>().expect("valid tokens"))
+            .collect::<Vec<_>>();
+        if xs.is_empty() {
+            quote!(false)
+        } else {
+            quote!(#(#xs)||*)
+        }
+    }
+}
+
+impl BParser {
+    /// Match arms for `Unparse`, `prefix` contains names of enclosing commands
+    fn unparse_arms(&self, prefix: &[&LitStr]) -> Result<Vec<UnparseArm>> {
+        // items inside of a command go after its name, for the outer parser it is positional
+        let command = (quote!(true), quote!(false));
+        Ok(match self {
+            BParser::Command(name, _, oparser) => {
+                let mut prefix = prefix.to_vec();
+                prefix.push(name);
+                oparser.inner.unparse_arms(&prefix)?
+            }
+            BParser::CargoHelper(name, inner) => {
+                let mut prefix = prefix.to_vec();
+                prefix.push(name);
+                inner.unparse_arms(&prefix)?
+            }
+            BParser::Constructor(con, fields) => {
+                let (pattern, bodies, kinds) = fields.unparse()?;
+                let (positional, named) = if prefix.is_empty() { kinds } else { command };
+                vec![UnparseArm {
+                    arm: quote! {
+                        #con #pattern => {
+                            #(let args = args.command(#prefix);)*
+                            #(#bodies)*
+                        }
+                    },
+                    positional,
+                    named,
+                }]
+            }
+            BParser::Singleton(flag) => {
+                let (value, name) = flag.unparse();
+                let (positional, named) = if prefix.is_empty() {
+                    (quote!(false), quote!(true))
+                } else {
+                    command
+                };
+                vec![UnparseArm {
+                    arm: quote! {
+                        #value => {
+                            #(let args = args.command(#prefix);)*
+                            args.flag(#name);
+                        }
+                    },
+                    positional,
+                    named,
+                }]
+            }
+            BParser::Fold(xs) => {
+                let mut res = Vec::new();
+                for x in xs {
+                    res.extend(x.unparse_arms(prefix)?);
+                }
+                res
+            }
+        })
     }
 }
 
@@ -422,6 +678,27 @@ impl ToTokens for ParserKind {
         match self {
             ParserKind::BParser(bp) => bp.to_tokens(tokens),
             ParserKind::OParser(op) => op.to_tokens(tokens),
+            ParserKind::Values(values, ignore_case) => {
+                quote!(::bpaf::Values::new()).to_tokens(tokens);
+                for EnumValue {
+                    value,
+                    name,
+                    aliases,
+                    help,
+                } in values
+                {
+                    quote!(.value(#name, #value)).to_tokens(tokens);
+                    for alias in aliases {
+                        quote!(.alias(#alias)).to_tokens(tokens);
+                    }
+                    if let Some(help) = help {
+                        quote!(.help(#help)).to_tokens(tokens);
+                    }
+                }
+                if *ignore_case {
+                    quote!(.ignore_case()).to_tokens(tokens);
+                }
+            }
         }
     }
 }
@@ -440,7 +717,7 @@ impl ToTokens for OParser {
 impl ToTokens for BParser {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         match self {
-            BParser::Command(cmd_name, oparser) => {
+            BParser::Command(cmd_name, extra, oparser) if extra.is_empty() => {
                 let help = match &oparser.decor.descr {
                     Some(msg) => quote!(Some(#msg)),
                     None => quote!(None::<String>),
@@ -451,6 +728,24 @@ impl ToTokens for BParser {
                 })
                 .to_tokens(tokens);
             }
+            BParser::Command(cmd_name, extra, oparser) => {
+                let help = oparser.decor.descr.as_ref().map(|msg| quote!(.help(#msg)));
+                let aliases = &extra.aliases;
+                let hidden_aliases = &extra.hidden_aliases;
+                let prefix = extra.allow_prefix.then(|| quote!(.allow_prefix()));
+                let default = extra.default.then(|| quote!(.default_command()));
+                quote!({
+                    let inner_cmd = #oparser;
+                    ::bpaf::Command::new(#cmd_name)
+                        #help
+                        #(.alias(#aliases))*
+                        #(.hidden_alias(#hidden_aliases))*
+                        #prefix
+                        #default
+                        .for_parser(inner_cmd)
+                })
+                .to_tokens(tokens);
+            }
             BParser::CargoHelper(name, inner) => quote!({
                 ::bpaf::cargo_helper(#name, #inner)
             })
@@ -542,6 +837,73 @@ impl Fields {
         }
     }
 
+    /// Pattern binding all the fields, code that adds items for every field and constant
+    /// expressions telling if positional and named items are added
+    ///
+    /// Fields are bound to generated names so they don't shadow functions used by `values`.
+    /// Named items go before positional ones in `UnparseArgs` so a named field can't follow
+    /// a positional one: direct fields are checked here, `external` and `flatten` fields when
+    /// the generated code is compiled.
+    #[allow(clippy::type_complexity)]
+    fn unparse(&self) -> Result<(TokenStream, Vec<TokenStream>, (TokenStream, TokenStream))> {
+        let var = |ix| Ident::new(&format!("bpaf_f{ix}"), Span::call_site());
+        let bodies = |fields: &Punctuated<FieldParser, Token![,]>| {
+            let mut positional_seen = false;
+            for field in fields {
+                match field.is_positional() {
+                    Some(true) => positional_seen = true,
+                    Some(false) if positional_seen => {
+                        let span = field.name().map_or_else(Span::call_site, Ident::span);
+                        return Err(syn::Error::new(
+                            span,
+                            "Can't unparse a named field that follows a positional one, \
+                            move positional fields to the end",
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+            let kinds = fields
+                .iter()
+                .map(FieldParser::unparse_kinds)
+                .collect::<Vec<_>>();
+            let mut res = Vec::new();
+            if fields.iter().any(|field| field.is_positional().is_none()) {
+                let conds = (1..kinds.len()).map(|ix| {
+                    let named = &kinds[ix].1;
+                    let positional = kinds[..ix].iter().map(|k| &k.0);
+                    quote!((#named && (#(#positional)||*)))
+                });
+                res.push(quote! {
+                    const _: () = ::std::assert!(
+                        !(false #(|| #conds)*),
+                        "Can't unparse a field with named items that follows positional items"
+                    );
+                });
+            }
+            for (ix, field) in fields.iter().enumerate() {
+                res.push(field.unparse(&var(ix))?);
+            }
+            let positional = any_of(kinds.iter().map(|k| &k.0));
+            let named = any_of(kinds.iter().map(|k| &k.1));
+            Ok((res, (positional, named)))
+        };
+        Ok(match self {
+            Fields::Named(fields) => {
+                let names = fields.iter().enumerate().map(|(ix, f)| f.var_name(ix));
+                let vars = (0..fields.len()).map(var);
+                let (bodies, kinds) = bodies(fields)?;
+                (quote!({ #(#names: #vars),* }), bodies, kinds)
+            }
+            Fields::Unnamed(fields) => {
+                let vars = (0..fields.len()).map(var);
+                let (bodies, kinds) = bodies(fields)?;
+                (quote!(( #(#vars),* )), bodies, kinds)
+            }
+            Fields::NoFields => (quote!(), Vec::new(), (quote!(false), quote!(false))),
+        })
+    }
+
     const fn struct_definition_followed_by_semi(&self) -> bool {
         match self {
             Fields::Named(_) | Fields::NoFields => false,
@@ -770,6 +1132,85 @@ mod test {
         assert_eq!(input.to_token_stream().to_string(), expected.to_string());
     }
 
+    #[test]
+    fn enum_values() {
+        let input: Top = parse_quote! {
+            #[bpaf(values(ignore_case))]
+            enum Format {
+                /// Plain text
+                PlainText,
+                #[bpaf(rename("md"), alias("markdown"))]
+                /// Markdown
+                Markdown,
+                Html,
+            }
+        };
+
+        let expected = quote! {
+            fn format() -> ::bpaf::Values<Format> {
+                ::bpaf::Values::new()
+                    .value("plain-text", Format::PlainText)
+                    .help("Plain text")
+                    .value("md", Format::Markdown)
+                    .alias("markdown")
+                    .help("Markdown")
+                    .value("html", Format::Html)
+                    .ignore_case()
+            }
+        };
+        assert_eq!(input.to_token_stream().to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn enum_command_aliases() {
+        let input: Top = parse_quote! {
+            enum Opt {
+                /// Remove a file
+                #[bpaf(command, alias("rm"), hidden_alias("delete"), allow_prefix)]
+                Remove,
+                #[bpaf(command("check"), default_command)]
+                Check { field: bool },
+            }
+        };
+
+        let expected = quote! {
+            fn opt() -> ::bpaf::Parser<Opt> {
+                {
+                    let alt0 = {
+                        let inner_cmd = {
+                            let inner_op = ::bpaf::Parser::pure(Opt::Remove);
+                            ::bpaf::Info::default().descr("Remove a file").for_parser(inner_op)
+                        };
+                        ::bpaf::Command::new("remove")
+                            .help("Remove a file")
+                            .alias("rm")
+                            .hidden_alias("delete")
+                            .allow_prefix()
+                            .for_parser(inner_cmd)
+                    };
+                    let alt1 = {
+                        let inner_cmd = {
+                            let inner_op = {
+                                let field = ::bpaf::long("field").switch();
+                                #[allow(unused_imports)]
+                                use bpaf::construct;
+                                construct!(Opt::Check { field })
+                            };
+                            ::bpaf::Info::default().for_parser(inner_op)
+                        };
+                        ::bpaf::Command::new("check")
+                            .default_command()
+                            .for_parser(inner_cmd)
+                    };
+                    #[allow(unused_imports)]
+                    use bpaf::construct;
+                    construct!([alt0, alt1])
+                }
+            }
+        };
+        assert_eq!(input.to_token_stream().to_string(), expected.to_string());
+    }
+
     #[test]
     fn unnamed_struct() {
         let top: Top = parse_quote! {
@@ -878,4 +1319,102 @@ mod test {
         };
         assert_eq!(top.to_token_stream().to_string(), expected.to_string());
     }
+
+    #[test]
+    fn enum_unparse() {
+        let top: Top = parse_quote! {
+            #[bpaf(unparse)]
+            enum Opt {
+                #[bpaf(command("add"))]
+                Add {
+                    #[bpaf(short)]
+                    name: Option<String>,
+                    #[bpaf(positional_os("FILE"))]
+                    files: Vec<PathBuf>,
+                },
+                #[bpaf(long("quiet"))]
+                Quiet,
+            }
+        };
+
+        let expected = quote! {
+            fn opt() -> ::bpaf::Parser<Opt> {
+                {
+                    let alt0 = {
+                        let inner_cmd = {
+                            let inner_op = {
+                                let name = ::bpaf::short('n').argument("ARG").optional();
+                                let files = ::bpaf::positional_os("FILE").map(PathBuf::from).many();
+                                #[allow(unused_imports)]
+                                use bpaf::construct;
+                                construct!(Opt::Add { name, files })
+                            };
+                            ::bpaf::Info::default().for_parser(inner_op)
+                        };
+                        ::bpaf::command("add", None::<String>, inner_cmd)
+                    };
+                    let alt1 = ::bpaf::long("quiet").req_flag(Opt::Quiet);
+                    #[allow(unused_imports)]
+                    use bpaf::construct;
+                    construct!([alt0, alt1])
+                }
+            }
+            impl ::bpaf::Unparse for Opt {
+                const POSITIONAL: bool = true;
+                const NAMED: bool = true;
+                fn unparse(&self, args: &mut ::bpaf::UnparseArgs) {
+                    match self {
+                        Opt::Add { name: bpaf_f0, files: bpaf_f1 } => {
+                            let args = args.command("add");
+                            if let Some(v) = bpaf_f0 {
+                                args.named(
+                                    "-n",
+                                    ::std::ffi::OsString::from(::std::string::ToString::to_string(v))
+                                );
+                            }
+                            for v in bpaf_f1 {
+                                args.positional(::std::ffi::OsString::from(
+                                    ::std::convert::AsRef::<::std::ffi::OsStr>::as_ref(v)
+                                ));
+                            }
+                        }
+                        Opt::Quiet => {
+                            args.flag("--quiet");
+                        }
+                    }
+                }
+            }
+        };
+        assert_eq!(top.to_token_stream().to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn unparse_map_is_an_error() {
+        let input = quote! {
+            #[bpaf(unparse)]
+            struct Opt {
+                #[bpaf(argument("N"), map(double))]
+                n: u32,
+            }
+        };
+        let err = syn::parse2::<Top>(input).unwrap_err().to_string();
+        assert_eq!(err, "Can't unparse a field with `map` or `parse`");
+    }
+
+    #[test]
+    fn unparse_named_after_positional_is_an_error() {
+        let input = quote! {
+            #[bpaf(unparse)]
+            struct Opt {
+                #[bpaf(positional("FROM"))]
+                from: String,
+                name: String,
+            }
+        };
+        let err = syn::parse2::<Top>(input).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Can't unparse a named field that follows a positional one, move positional fields to the end"
+        );
+    }
 }
@@ -1,37 +1,4 @@
-use proc_macro2::{Ident, Span};
-use syn::{
-    parse::{Parse, ParseStream},
-    spanned::Spanned,
-    LitStr, Token,
-};
-
-struct Doc(pub String);
-impl Parse for Doc {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        input.parse::<Token![=]>()?;
-        let s = input.parse::<LitStr>()?.value();
-        Ok(Doc(s.trim_start().to_string()))
-    }
-}
-
-impl<T> Spanned for WithSpan<T> {
-    fn span(&self) -> Span {
-        self.span
-    }
-}
-
-pub struct WithSpan<T> {
-    pub value: T,
-    pub span: Span,
-}
-
-impl<T: Parse> Parse for WithSpan<T> {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let span = input.span();
-        let value = input.parse::<T>()?;
-        Ok(WithSpan { value, span })
-    }
-}
+use proc_macro2::Ident;
 
 pub fn to_snake_case(input: &str) -> String {
     to_custom_case(input, '_')
@@ -44,7 +11,7 @@ pub fn to_kebab_case(input: &str) -> String {
 pub fn to_custom_case(input: &str, sep: char) -> String {
     let mut res = String::with_capacity(input.len() * 2);
     for c in input.chars() {
-        if ('A'..='Z').contains(&c) {
+        if c.is_ascii_uppercase() {
             if !res.is_empty() {
                 res.push(sep);
             }
@@ -121,3 +88,15 @@ impl Iterator for LineIter<'_> {
         }
     }
 }
+
+/// Help message from a doc comment: the part before the first double empty line and the whole
+/// comment if there's more
+pub fn split_help(help: &[String]) -> (Option<String>, Option<String>) {
+    let parts = LineIter::from(help).collect::<Vec<_>>();
+    let long_help = if parts.len() > 1 {
+        Some(parts.join("\n\n"))
+    } else {
+        None
+    };
+    (parts.into_iter().next(), long_help)
+}
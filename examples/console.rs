@@ -0,0 +1,59 @@
+//! Interactive console where every line is a command, the same parser can be used for command
+//! line options
+use bpaf::*;
+use std::io::BufRead;
+
+#[derive(Debug, Clone)]
+enum Cmd {
+    Add { name: String, force: bool },
+    Remove { names: Vec<String> },
+    Quit,
+}
+
+fn cmd() -> OptionParser<Cmd> {
+    let name = positional("NAME");
+    let force = short('f')
+        .long("force")
+        .help("Replace existing name")
+        .switch();
+    let add = Info::default()
+        .descr("Add a name")
+        .for_parser(construct!(Cmd::Add { name, force }));
+    let add = command("add", Some("Add a name"), add);
+
+    let names = positional("NAME").many();
+    let remove = Info::default()
+        .descr("Remove names")
+        .for_parser(construct!(Cmd::Remove { names }));
+    let remove = command("rm", Some("Remove names"), remove);
+
+    let quit = Info::default().for_parser(Parser::pure(Cmd::Quit));
+    let quit = command("quit", Some("Exit the console"), quit);
+    Info::default().for_parser(construct!([add, remove, quit]))
+}
+
+fn main() {
+    let parser = cmd();
+    let mut known = Vec::new();
+    for line in std::io::stdin().lock().lines() {
+        let line = line.unwrap();
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parser.run_line(&line) {
+            Ok(Cmd::Add { name, force }) => {
+                if force || !known.contains(&name) {
+                    known.retain(|n| n != &name);
+                    known.push(name);
+                } else {
+                    println!("{} is already known, use --force to replace it", name);
+                }
+            }
+            Ok(Cmd::Remove { names }) => known.retain(|n| !names.contains(n)),
+            Ok(Cmd::Quit) => break,
+            Err(ParseFailure::Stdout(msg)) => println!("{}", msg),
+            Err(ParseFailure::Stderr(msg)) => println!("error: {}", msg),
+        }
+        println!("Known names: {:?}", known);
+    }
+}
@@ -3,7 +3,7 @@ use bpaf::*;
 #[derive(Debug, Clone, Bpaf)]
 #[bpaf(options("hackerman"))]
 pub enum Action {
-    #[bpaf(command("explain"))]
+    #[bpaf(command("explain"), alias("x"), allow_prefix)]
     Explain {
         #[bpaf(positional("CRATE"))]
         krate: String,
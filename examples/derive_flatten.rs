@@ -0,0 +1,34 @@
+//! Nesting derived parsers with `flatten` and reusing parser factories with `external`
+
+use bpaf::*;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Bpaf)]
+#[bpaf(options)]
+struct Opts {
+    /// switch verbosity on
+    verbose: bool,
+    #[bpaf(flatten("db"))]
+    db: Connection,
+    #[bpaf(flatten("cache"))]
+    cache: Option<Connection>,
+    #[bpaf(external(port("listen")))]
+    listen: u16,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Bpaf)]
+struct Connection {
+    /// host to connect to
+    host: String,
+    #[bpaf(external(port("port")))]
+    port: u16,
+}
+
+fn port(name: &'static str) -> Parser<u16> {
+    long(name).help("port number").argument("PORT").from_str()
+}
+
+fn main() {
+    println!("{:?}", opts().run());
+}
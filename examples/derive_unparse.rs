@@ -0,0 +1,48 @@
+//! Turning parsed options back into arguments, for example to run a copy of the program
+
+use bpaf::*;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Bpaf)]
+#[bpaf(values)]
+enum Level {
+    Low,
+    High,
+}
+
+#[derive(Debug, Clone, PartialEq, Bpaf)]
+#[bpaf(unparse)]
+struct Connection {
+    /// host to connect to
+    host: String,
+    /// port to connect to
+    port: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Bpaf)]
+#[bpaf(options, unparse)]
+enum Opts {
+    /// Run a worker
+    #[bpaf(command)]
+    Worker {
+        /// switch verbosity on
+        #[bpaf(short, long)]
+        verbose: bool,
+        #[bpaf(argument("LEVEL"), values(level))]
+        level: Level,
+        #[bpaf(flatten)]
+        connection: Connection,
+        #[bpaf(positional_os("FILE"))]
+        files: Vec<PathBuf>,
+    },
+}
+
+fn main() {
+    let parsed = opts().run();
+    let args = parsed.to_args();
+    println!("Worker command line: {:?}", args);
+
+    let args = args.iter().map(|a| a.as_os_str()).collect::<Vec<_>>();
+    let again = opts().run_inner(Args::from(args.as_slice())).unwrap();
+    assert_eq!(parsed, again);
+}
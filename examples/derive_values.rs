@@ -0,0 +1,29 @@
+//! fieldless enum as a set of values for a single argument
+use bpaf::Bpaf;
+
+#[derive(Debug, Clone, Bpaf)]
+#[bpaf(values(ignore_case))]
+enum Format {
+    /// Plain text
+    PlainText,
+    /// Markdown with links
+    #[bpaf(rename("md"), alias("markdown"))]
+    Markdown,
+    Html,
+}
+
+#[derive(Debug, Clone, Bpaf)]
+#[bpaf(options)]
+#[allow(dead_code)]
+struct Opts {
+    /// Optional format for the summary
+    #[bpaf(values(format))]
+    summary: Option<Format>,
+    /// Output format
+    #[bpaf(short, long, argument("FORMAT"), values(format))]
+    format: Format,
+}
+
+fn main() {
+    println!("{:?}", opts().run());
+}
@@ -1,6 +1,5 @@
 //! using enum flags
 use bpaf::*;
-use std::str::FromStr;
 
 #[derive(Debug, Clone)]
 enum Baz {
@@ -9,24 +8,16 @@ enum Baz {
     FooBar,
 }
 
-impl FromStr for Baz {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "foo" => Ok(Baz::Foo),
-            "bar" => Ok(Baz::Bar),
-            "foobar" => Ok(Baz::FooBar),
-            _ => Err("Expected foo|bar|foobar".to_string()),
-        }
-    }
-}
 fn main() {
+    let values = Values::new()
+        .value("foo", Baz::Foo)
+        .value("bar", Baz::Bar)
+        .value("foobar", Baz::FooBar);
     let arg: Parser<Baz> = long("baz")
         .short('b')
         .help("choose between foo, bar or foobar")
         .argument("CMD")
-        .from_str();
+        .values(values);
 
     let opt = Info::default().for_parser(arg).run();
     println!("{:#?}", opt);
@@ -11,6 +11,7 @@ struct Foo {
 }
 
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 enum Cmd {
     Accelerate(bool),
 }
@@ -1,4 +1,4 @@
-use crate::Error;
+use crate::info::{Error, ErrorContext};
 use std::ffi::OsString;
 
 /// Contains [`OsString`] with its [`String`] equivalent if encoding is utf8
@@ -10,12 +10,10 @@ pub struct Word {
 
 /// Hides [`Args`] internal implementation
 mod inner {
-    use std::{
-        ffi::{OsStr, OsString},
-        rc::Rc,
-    };
+    use std::ffi::{OsStr, OsString};
 
-    use super::{push_vec, Arg, Word};
+    use super::{push_vec, split_shell_words, Arg, Error, ErrorContext, Word};
+    use crate::Rc;
     /// All currently present command line parameters
     #[derive(Clone, Debug)]
     pub struct Args {
@@ -27,9 +25,36 @@ mod inner {
 
         /// Used to render an error message for [`parse`][crate::Parser::parse]
         pub(crate) current: Option<Word>,
+        /// index of the original argument `current` came from
+        pub(crate) current_origin: Option<usize>,
 
         /// used to pick the parser that consumes the left most item
         pub(crate) head: usize,
+
+        /// visible names of commands accepted at the current level, used to check if a command
+        /// prefix is unique
+        pub(crate) commands: Rc<[&'static str]>,
+        /// hidden aliases of commands accepted at the current level, a command prefix shared
+        /// with one of them is not unique either
+        pub(crate) hidden_commands: Rc<[&'static str]>,
+
+        /// first argument that can't be split into items along with its index, reported
+        /// before parsing
+        pub(crate) error: Option<(String, usize)>,
+
+        /// program name followed by names of commands parsed so far, used in usage lines
+        pub(crate) name: Option<Rc<str>>,
+
+        /// program name can select a command, see [`Args::multicall`]
+        multicall: bool,
+
+        /// command selected by the program name, other commands at this level don't match
+        pub(crate) selected: Option<&'static str>,
+
+        /// original command line arguments
+        argv: Rc<[OsString]>,
+        /// for each item - index of the original argument it was produced from
+        origins: Rc<[usize]>,
     }
 
     impl<const N: usize> From<&[&str; N]> for Args {
@@ -41,36 +66,218 @@ mod inner {
 
     impl From<&[&str]> for Args {
         fn from(xs: &[&str]) -> Self {
-            let mut pos_only = false;
-            let mut vec = Vec::with_capacity(xs.len());
-            for x in xs {
-                push_vec(&mut vec, OsString::from(x), &mut pos_only);
-            }
-            Args::from(vec)
+            Args::from_os_strings(xs.iter().map(OsString::from))
         }
     }
 
     impl From<&[&OsStr]> for Args {
         fn from(xs: &[&OsStr]) -> Self {
-            let mut pos_only = false;
-            let mut vec = Vec::with_capacity(xs.len());
-            for x in xs {
-                push_vec(&mut vec, OsString::from(x), &mut pos_only);
-            }
-            Args::from(vec)
+            Args::from_os_strings(xs.iter().map(OsString::from))
         }
     }
 
-    impl From<Vec<Arg>> for Args {
-        fn from(vec: Vec<Arg>) -> Self {
+    impl Args {
+        /// Split command line arguments into items, remembering where each item came from
+        pub(crate) fn from_os_strings<I>(xs: I) -> Self
+        where
+            I: IntoIterator<Item = OsString>,
+        {
+            let mut pos_only = false;
+            let mut vec = Vec::new();
+            let mut origins = Vec::new();
+            let mut argv = Vec::new();
+            let mut error = None;
+            for (ix, x) in xs.into_iter().enumerate() {
+                if let Err(err) = push_vec(&mut vec, x.clone(), &mut pos_only) {
+                    error.get_or_insert((err, ix));
+                }
+                origins.resize(vec.len(), ix);
+                argv.push(x);
+            }
             Args {
                 removed: vec![false; vec.len()],
                 remaining: vec.len(),
                 items: Rc::from(vec),
                 current: None,
+                current_origin: None,
                 head: usize::MAX,
+                commands: Rc::from(Vec::new()),
+                hidden_commands: Rc::from(Vec::new()),
+                error,
+                name: None,
+                multicall: false,
+                selected: None,
+                argv: Rc::from(argv),
+                origins: Rc::from(origins),
+            }
+        }
+
+        /// Split a string into arguments following shell quoting rules
+        ///
+        /// Words are separated by whitespace. Single quotes keep everything inside as is,
+        /// inside double quotes backslash escapes `$`, `` ` ``, `"`, `\\` and a newline,
+        /// outside of quotes it escapes any character. `#` at the beginning of a word starts
+        /// a comment until the end of the line. Variables, globs and other expansions are not
+        /// performed.
+        ///
+        /// ```rust
+        /// # use bpaf::*;
+        /// let name = long("name").argument("NAME");
+        /// let files = positional("FILE").many();
+        /// let parser = Info::default().for_parser(construct!(name, files));
+        ///
+        /// let args = Args::from_shell_str(r#"--name "Jane Doe" a\ b 'c d' # files"#).unwrap();
+        /// let (name, files) = parser.run_inner(args).unwrap();
+        /// assert_eq!(name, "Jane Doe");
+        /// assert_eq!(files, ["a b", "c d"]);
+        ///
+        /// let err = Args::from_shell_str("--name 'Jane").unwrap_err();
+        /// assert_eq!(err, "unterminated single quote starting at line 1, column 8");
+        /// ```
+        ///
+        /// # Errors
+        /// Returns an error with the position of an unterminated quote or a trailing backslash
+        pub fn from_shell_str(input: &str) -> Result<Self, String> {
+            let words = split_shell_words(input)?;
+            Ok(Args::from_os_strings(words.into_iter().map(OsString::from)))
+        }
+
+        /// Set the program name to use in usage lines of the help
+        ///
+        /// Names of commands are appended to it in the help for nested commands.
+        ///
+        /// ```rust
+        /// # use bpaf::*;
+        /// let parser = Info::default().for_parser(short('v').switch());
+        /// let args = Args::from(&["--help"]).name("app");
+        /// let help = parser.run_inner(args).unwrap_err().unwrap_stdout();
+        /// assert!(help.starts_with("Usage: app [-v]\n"));
+        /// ```
+        #[must_use]
+        pub fn name(mut self, name: &str) -> Self {
+            self.name = Some(Rc::from(name));
+            self
+        }
+
+        /// Use a command selected by the name the program is invoked with, for multicall binaries
+        ///
+        /// Base name of `argv0` without directories and executable extension becomes the program
+        /// name, see [`name`](Args::name). If it is equal to a name of one of the top level
+        /// commands or ends with `-` followed by a name - all the arguments are passed to this
+        /// command as if it was given in the arguments: `mytool-fmt file` or `fmt file` work as
+        /// `mytool fmt file`. Otherwise arguments are parsed as usual.
+        ///
+        /// See also [`OptionParser::run_multicall`].
+        ///
+        /// ```rust
+        /// # use bpaf::*;
+        /// let fmt = command("fmt", Some("Format files"), Info::default().for_parser(positional("FILE")));
+        /// let parser = Info::default().for_parser(fmt);
+        ///
+        /// let args = Args::from(&["a.rs"]).multicall("/usr/bin/mytool-fmt");
+        /// assert_eq!(parser.run_line("fmt a.rs").unwrap(), parser.clone().run_inner(args).unwrap());
+        /// ```
+        #[must_use]
+        pub fn multicall<S: AsRef<OsStr>>(mut self, argv0: S) -> Self {
+            let path = std::path::Path::new(argv0.as_ref());
+            let name = path
+                .file_name()
+                .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+            let suffix = std::env::consts::EXE_SUFFIX;
+            let name = match name.strip_suffix(suffix) {
+                Some(stem) if !suffix.is_empty() && !stem.is_empty() => stem,
+                _ => name.as_str(),
+            };
+            self.name = Some(Rc::from(name));
+            self.multicall = true;
+            self
+        }
+
+        /// Pick a command selected by the program name, if any
+        ///
+        /// `commands` contain all the names of every top level command, primary name first
+        pub(crate) fn select_command(&mut self, commands: &[Vec<&'static str>]) {
+            if !std::mem::take(&mut self.multicall) {
+                return;
+            }
+            let prog = match &self.name {
+                Some(prog) => prog.clone(),
+                None => return,
+            };
+            let selects = |n: &&str| {
+                prog.as_ref() == *n || prog.strip_suffix(n).is_some_and(|p| p.ends_with('-'))
+            };
+            self.selected = commands
+                .iter()
+                .find(|names| names.iter().any(selects))
+                .map(|names| names[0]);
+        }
+
+        /// Update the program name when parsing continues inside of a command
+        ///
+        /// `name` is `None` if command was selected by the program name
+        pub(crate) fn enter_command(&mut self, name: Option<&str>) {
+            self.selected = None;
+            if let (Some(prog), Some(name)) = (&self.name, name) {
+                self.name = Some(Rc::from(format!("{} {}", prog, name)));
             }
         }
+
+        /// Error caused by the original argument with index `origin`
+        pub(crate) fn error_at_origin(&self, origin: usize, message: String) -> Error {
+            Error::Context(Box::new(ErrorContext {
+                message,
+                position: Some((self.argv.clone(), origin)),
+                usage: None,
+                help_flag: None,
+                missing: false,
+            }))
+        }
+
+        /// Index of the original argument the item came from
+        pub(crate) fn origin(&self, ix: usize) -> usize {
+            self.origins[ix]
+        }
+
+        /// Error caused by an item, points to the original argument the item came from
+        pub(crate) fn error_at(&self, ix: usize, message: String) -> Error {
+            self.error_at_origin(self.origin(ix), message)
+        }
+
+        /// Error caused by the last value taken, see [`current`](Args::current)
+        pub(crate) fn error_at_current(&self, message: String) -> Error {
+            match self.current_origin {
+                Some(origin) => self.error_at_origin(origin, message),
+                None => Error::Stderr(message),
+            }
+        }
+
+        /// Take the first remaining item if it is a word along with all the original
+        /// arguments following it that are not consumed yet
+        pub(crate) fn take_word_and_rest(&mut self) -> Option<(String, Vec<OsString>)> {
+            let (ix, word) = match self.items_iter().next() {
+                Some((ix, Arg::Word(Word { utf8: Some(w), .. }))) => (ix, w.clone()),
+                _ => return None,
+            };
+
+            // original arguments that produced no items such as "--" are kept as is,
+            // others are kept if at least one of their items is still present
+            let mut produced = vec![false; self.argv.len()];
+            let mut present = vec![false; self.argv.len()];
+            for (item, &origin) in self.origins.iter().enumerate() {
+                produced[origin] = true;
+                present[origin] |= !self.removed[item] && item != ix;
+            }
+            let rest = (self.origins[ix] + 1..self.argv.len())
+                .filter(|&o| present[o] || !produced[o])
+                .map(|o| self.argv[o].clone())
+                .collect::<Vec<_>>();
+
+            for item in 0..self.items.len() {
+                self.remove(item);
+            }
+            Some((word, rest))
+        }
     }
 
     pub struct ArgsIter<'a> {
@@ -92,6 +299,13 @@ mod inner {
             self.removed[index] = true;
         }
 
+        /// First item consumed since `before`, `before` must be a copy of the same arguments
+        pub(crate) fn removed_since(&self, before: &Args) -> Option<&Arg> {
+            (0..self.items.len())
+                .find(|&ix| self.removed[ix] && !before.removed[ix])
+                .map(|ix| &self.items[ix])
+        }
+
         pub(crate) const fn is_empty(&self) -> bool {
             self.remaining == 0
         }
@@ -99,6 +313,70 @@ mod inner {
         pub(crate) const fn len(&self) -> usize {
             self.remaining
         }
+
+        /// Run a parser on a view where only long names starting with `prefix-` are visible
+        ///
+        /// Prefix is stripped from visible names, items consumed by the parser are removed
+        /// from the original arguments.
+        pub(crate) fn with_long_prefix<T, F>(
+            mut self,
+            prefix: &str,
+            parse: F,
+        ) -> Result<(T, Args), Error>
+        where
+            F: FnOnce(Args) -> Result<(T, Args), Error>,
+        {
+            let mut hidden = vec![false; self.items.len()];
+            let items = self
+                .items
+                .iter()
+                .enumerate()
+                .map(|(ix, arg)| match arg {
+                    Arg::Long(l) => {
+                        match l.strip_prefix(prefix).and_then(|l| l.strip_prefix('-')) {
+                            Some(name) => Arg::Long(name.to_owned()),
+                            None => {
+                                hidden[ix] = true;
+                                arg.clone()
+                            }
+                        }
+                    }
+                    Arg::Short(_) | Arg::Word(_) => arg.clone(),
+                })
+                .collect::<Vec<_>>();
+            let removed = self
+                .removed
+                .iter()
+                .zip(hidden.iter())
+                .map(|(r, h)| *r || *h)
+                .collect::<Vec<_>>();
+            let view = Args {
+                items: Rc::from(items),
+                remaining: removed.iter().filter(|r| !**r).count(),
+                removed,
+                current: self.current.clone(),
+                current_origin: self.current_origin,
+                head: self.head,
+                commands: self.commands.clone(),
+                hidden_commands: self.hidden_commands.clone(),
+                error: None,
+                name: self.name.clone(),
+                multicall: self.multicall,
+                selected: self.selected,
+                argv: self.argv.clone(),
+                origins: self.origins.clone(),
+            };
+
+            let (t, view) = parse(view)?;
+            for (ix, hidden) in hidden.into_iter().enumerate() {
+                if view.removed[ix] && !hidden {
+                    self.remove(ix);
+                }
+            }
+            self.current = view.current;
+            self.current_origin = view.current_origin;
+            Ok((t, self))
+        }
     }
 
     impl<'a> Iterator for ArgsIter<'a> {
@@ -157,15 +435,23 @@ impl Arg {
     }
 }
 
-pub(crate) fn push_vec(vec: &mut Vec<Arg>, os: OsString, pos_only: &mut bool) {
+/// Split a single argument into items
+///
+/// Fails for arguments that can't be represented as items such as `-ab=1`
+pub(crate) fn push_vec(
+    vec: &mut Vec<Arg>,
+    os: OsString,
+    pos_only: &mut bool,
+) -> Result<(), String> {
     // if we are after "--" sign or there's no utf8 representation for
     // an item - it can only be a positional argument
     let utf8 = match (*pos_only, os.to_str()) {
         (true, v) | (_, v @ None) => {
-            return vec.push(Arg::Word(Word {
+            vec.push(Arg::Word(Word {
                 utf8: v.map(String::from),
                 os,
-            }))
+            }));
+            return Ok(());
         }
         (false, Some(x)) => x,
     };
@@ -184,12 +470,16 @@ pub(crate) fn push_vec(vec: &mut Vec<Arg>, os: OsString, pos_only: &mut bool) {
         }
     } else if let Some(body) = utf8.strip_prefix('-') {
         if let Some((key, val)) = body.split_once('=') {
-            assert_eq!(
-                key.len(),
-                1,
-                "short flag with argument must have only one key"
-            );
-            let key = key.chars().next().expect("key should be one character");
+            let mut chars = key.chars();
+            let key = match (chars.next(), chars.next()) {
+                (Some(key), None) => key,
+                _ => {
+                    return Err(format!(
+                        "{} is not a valid argument, short flag with a value must have exactly one name",
+                        utf8
+                    ))
+                }
+            };
             vec.push(Arg::Short(key));
             vec.push(Arg::Word(Word {
                 utf8: Some(val.to_owned()),
@@ -206,6 +496,79 @@ pub(crate) fn push_vec(vec: &mut Vec<Arg>, os: OsString, pos_only: &mut bool) {
             os,
         }));
     }
+    Ok(())
+}
+
+/// Split a line into words following shell quoting rules
+///
+/// Words are separated by whitespace, single quotes keep everything literally, double quotes
+/// allow escaping `$`, `` ` ``, `"` and `\` with a backslash, outside of quotes backslash escapes
+/// any character. Backslash followed by a newline is removed. `#` at the beginning of a word
+/// starts a comment that lasts until the end of the line.
+pub(crate) fn split_shell_words(input: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = input.char_indices();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some((_, '\'')) => break,
+                        Some((_, c)) => word.push(c),
+                        None => return Err(unterminated(input, start, "single quote")),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c @ ('$' | '`' | '"' | '\\'))) => word.push(c),
+                            Some((_, '\n')) => {}
+                            Some((_, c)) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(unterminated(input, start, "double quote")),
+                        },
+                        Some((_, c)) => word.push(c),
+                        None => return Err(unterminated(input, start, "double quote")),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some((_, '\n')) => {}
+                Some((_, c)) => word.get_or_insert_with(String::new).push(c),
+                None => return Err(unterminated(input, start, "backslash escape")),
+            },
+            '#' if word.is_none() => {
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Error message for a quote or an escape starting at byte `offset` that never ends
+fn unterminated(input: &str, offset: usize, what: &str) -> String {
+    let before = &input[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    format!(
+        "unterminated {} starting at line {}, column {}",
+        what, line, column
+    )
 }
 
 impl Args {
@@ -241,14 +604,14 @@ impl Args {
         let (val_ix, val) = match iter.next() {
             Some((ix, Arg::Word(w))) => (ix, w),
             Some((_ix, flag)) => {
-                return Err(Error::Stderr(format!(
-                    "{arg} requires an argument, got flag {flag}"
-                )))
+                let message = format!("{arg} requires an argument, got flag {flag}");
+                return Err(self.error_at(key_ix, message));
             }
-            _ => return Err(Error::Stderr(format!("{arg} requires an argument"))),
+            _ => return Err(self.error_at(key_ix, format!("{arg} requires an argument"))),
         };
         let val = val.clone();
         self.current = Some(val.clone());
+        self.current_origin = Some(self.origin(val_ix));
         self.remove(key_ix);
         self.remove(val_ix);
         Ok(Some(val))
@@ -263,10 +626,11 @@ impl Args {
             Some((ix, Arg::Word(w))) => {
                 let w = w.clone();
                 self.current = Some(w.clone());
+                self.current_origin = Some(self.origin(ix));
                 self.remove(ix);
                 Ok(Some(w))
             }
-            Some((_, arg)) => Err(Error::Stderr(format!("Expected an argument, got {arg}"))),
+            Some((ix, arg)) => Err(self.error_at(ix, format!("Expected an argument, got {arg}"))),
             None => Ok(None),
         }
     }
@@ -274,7 +638,7 @@ impl Args {
     /// take a static string argument from the first present argument
     pub(crate) fn take_cmd(&mut self, word: &'static str) -> bool {
         if let Some((ix, Arg::Word(w))) = self.items_iter().next() {
-            if w.utf8.as_ref().map_or(false, |ww| ww == word) {
+            if w.utf8.as_ref().is_some_and(|ww| ww == word) {
                 self.remove(ix);
                 return true;
             }
@@ -282,14 +646,89 @@ impl Args {
         false
     }
 
+    /// take a command given as a prefix of one of the `names` if no other command
+    /// at this level starts with the same prefix, including hidden aliases of other commands
+    pub(crate) fn take_cmd_prefix(
+        &mut self,
+        names: &[&'static str],
+        hidden_names: &[&'static str],
+    ) -> bool {
+        let ix = match self.items_iter().next() {
+            Some((ix, Arg::Word(Word { utf8: Some(w), .. }))) if !w.is_empty() => {
+                let ours = names.iter().any(|n| n.starts_with(w.as_str()));
+                let theirs = self
+                    .commands
+                    .iter()
+                    .chain(self.hidden_commands.iter())
+                    .any(|n| {
+                        !names.contains(n) && !hidden_names.contains(n) && n.starts_with(w.as_str())
+                    });
+                if ours && !theirs {
+                    ix
+                } else {
+                    return false;
+                }
+            }
+            _ => return false,
+        };
+        self.remove(ix);
+        true
+    }
+
     pub(crate) fn peek(&self) -> Option<&Arg> {
         self.items_iter().next().map(|x| x.1)
     }
+
+    /// first remaining item is a name or an alias of a command at this level
+    pub(crate) fn peek_is_command(&self) -> bool {
+        match self.peek() {
+            Some(Arg::Word(Word { utf8: Some(w), .. })) => self
+                .commands
+                .iter()
+                .chain(self.hidden_commands.iter())
+                .any(|n| n == w),
+            _ => false,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[test]
+    fn word_and_rest() {
+        let mut a = Args::from(&["-v", "cmd", "-abc", "-v", "--", "--x=1"]);
+        assert!(a.take_flag(|f| f.is_short('v')));
+        // second -v is consumed by some other parser before the command
+        assert!(a.take_flag(|f| f.is_short('v')));
+        let (cmd, rest) = a.take_word_and_rest().unwrap();
+        assert_eq!(cmd, "cmd");
+        assert_eq!(rest, ["-abc", "--", "--x=1"]);
+        assert!(a.is_empty());
+    }
+
+    #[test]
+    fn shell_words() {
+        let words = split_shell_words(r#"add  'a b' "c \"d\" \e" f\ g '' x"'y'"\\"#).unwrap();
+        assert_eq!(words, ["add", "a b", "c \"d\" \\e", "f g", "", "x'y'\\"]);
+        assert_eq!(split_shell_words(" \t").unwrap(), Vec::<String>::new());
+        assert_eq!(split_shell_words("a\\\nb").unwrap(), ["ab"]);
+        let words = split_shell_words("# comment\nrun a#b # c 'd'\n\\# '#'").unwrap();
+        assert_eq!(words, ["run", "a#b", "#", "#"]);
+        assert_eq!(
+            split_shell_words("echo 'a").unwrap_err(),
+            "unterminated single quote starting at line 1, column 6"
+        );
+        assert_eq!(
+            split_shell_words("echo\n  é \"a\\\"").unwrap_err(),
+            "unterminated double quote starting at line 2, column 5"
+        );
+        assert_eq!(
+            split_shell_words("echo a\\").unwrap_err(),
+            "unterminated backslash escape starting at line 1, column 7"
+        );
+    }
+
     #[test]
     fn long_arg() {
         let mut a = Args::from(&["--speed", "12"]);
@@ -350,6 +789,21 @@ mod tests {
         assert!(a.is_empty());
     }
 
+    #[test]
+    fn short_arg_with_equality_and_many_names() {
+        let a = Args::from(&["-ab=1", "-=", "-é=1"]);
+        assert_eq!(
+            a.error.as_ref().map(|e| e.0.as_str()),
+            Some(
+                "-ab=1 is not a valid argument, short flag with a value must have exactly one name"
+            )
+        );
+        let mut a = Args::from(&["-é=1"]);
+        assert_eq!(a.error, None);
+        let s = a.take_arg(|f| f.is_short('é')).unwrap().unwrap();
+        assert_eq!(s.utf8.unwrap(), "1");
+    }
+
     #[test]
     fn short_arg_without_equality() {
         let mut a = Args::from(&["-s", "12"]);
@@ -0,0 +1,351 @@
+//! Reference documentation in Markdown and HTML formats
+#![allow(clippy::write_with_newline)]
+use std::fmt::Write;
+
+use crate::info::{Item, ItemKind, Meta};
+use crate::{Info, OptionParser};
+
+/// A parser or a subparser of a command, along with names of commands leading to it
+pub(crate) struct Section<'a> {
+    pub(crate) path: Vec<&'a str>,
+    pub(crate) info: Info,
+    pub(crate) parser_meta: Meta,
+    pub(crate) help_meta: Meta,
+}
+
+fn collect_sections<'a>(
+    path: Vec<&'a str>,
+    info: &Info,
+    parser_meta: &Meta,
+    help_meta: &Meta,
+    res: &mut Vec<Section<'a>>,
+) {
+    let commands = parser_meta.commands();
+    res.push(Section {
+        path: path.clone(),
+        info: info.clone(),
+        parser_meta: parser_meta.clone(),
+        help_meta: help_meta.clone(),
+    });
+    for cmd in commands {
+        if let (Some(name), Some(sub)) = (cmd.long, &cmd.subparser) {
+            let mut path = path.clone();
+            path.push(name);
+            collect_sections(path, &sub.info, &sub.parser_meta, &sub.help_meta, res);
+        }
+    }
+}
+
+impl Section<'_> {
+    fn title(&self) -> String {
+        self.path.join(" ")
+    }
+
+    fn anchor(&self) -> String {
+        anchor(&self.path)
+    }
+
+    /// Parser items together with `--help` and `--version`
+    fn meta(&self) -> Meta {
+        Meta::and(self.parser_meta.clone(), self.help_meta.clone())
+    }
+
+    fn usage(&self) -> String {
+        match self.info.usage {
+            Some(usage) => usage.to_owned(),
+            None => self
+                .info
+                .render_usage(&self.parser_meta, Some(&self.title()), 0),
+        }
+    }
+}
+
+fn anchor(path: &[&str]) -> String {
+    path.join("-")
+}
+
+/// Names of a flag or an argument as shown in the help: `-v`, `--verbose <ARG>`
+fn flag_name(item: &Item) -> String {
+    let mut res = String::new();
+    if let Some(s) = item.short {
+        write!(res, "-{}", s).unwrap();
+    }
+    if let Some(l) = item.long {
+        if !res.is_empty() {
+            res.push_str(", ");
+        }
+        write!(res, "--{}", l).unwrap();
+    }
+    if let Some(m) = item.metavar {
+        if !res.is_empty() {
+            res.push(' ');
+        }
+        write!(res, "<{}>", m).unwrap();
+    }
+    res
+}
+
+fn markdown_cell(s: &str) -> String {
+    s.replace('|', "\\|").replace('\n', "<br>")
+}
+
+fn html_escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+/// Name of an item for a table row and its detailed help
+fn item_name(item: &Item) -> (String, String) {
+    let name = match item.kind {
+        ItemKind::Positional => format!("<{}>", item.positional_metavar()),
+        _ => flag_name(item),
+    };
+    let help = item.clone().long_form().help_with_default();
+    (name, help.unwrap_or_default())
+}
+
+/// Output format for the reference documentation, [`render`] walks the sections and uses
+/// this to write the pieces
+trait Format {
+    /// Section title, the first section is the top level one
+    fn title(&self, res: &mut String, section: &Section, top: bool);
+    /// Description, header or footer
+    fn paragraph(&self, res: &mut String, text: &str);
+    fn usage(&self, res: &mut String, usage: &str);
+    fn table_title(&self, res: &mut String, title: &str);
+    fn table_start(&self, res: &mut String);
+    fn table_end(&self, res: &mut String);
+    /// Row with a group header
+    fn group_row(&self, res: &mut String, help: &str);
+    /// Row with a name made with [`code`](Format::code) and [`link`](Format::link) and help
+    fn row(&self, res: &mut String, name: &str, help: &str);
+    fn code(&self, text: &str) -> String;
+    fn link(&self, text: &str, anchor: &str) -> String;
+}
+
+struct Markdown;
+
+impl Format for Markdown {
+    fn title(&self, res: &mut String, section: &Section, top: bool) {
+        if top {
+            write!(res, "# {}\n", section.title()).unwrap();
+        } else {
+            write!(res, "\n<a id=\"{}\"></a>\n\n", section.anchor()).unwrap();
+            write!(res, "## {}\n", section.title()).unwrap();
+        }
+    }
+
+    fn paragraph(&self, res: &mut String, text: &str) {
+        write!(res, "\n{}\n", text).unwrap();
+    }
+
+    fn usage(&self, res: &mut String, usage: &str) {
+        write!(res, "\n```text\n{}\n```\n", usage).unwrap();
+    }
+
+    fn table_title(&self, res: &mut String, title: &str) {
+        write!(res, "\n### {}\n", title).unwrap();
+    }
+
+    fn table_start(&self, res: &mut String) {
+        res.push_str("\n| Name | Description |\n| --- | --- |\n");
+    }
+
+    fn table_end(&self, _res: &mut String) {}
+
+    fn group_row(&self, res: &mut String, help: &str) {
+        write!(res, "| **{}** | |\n", markdown_cell(help)).unwrap();
+    }
+
+    fn row(&self, res: &mut String, name: &str, help: &str) {
+        write!(res, "| {} | {} |\n", name, markdown_cell(help)).unwrap();
+    }
+
+    fn code(&self, text: &str) -> String {
+        format!("`{}`", text)
+    }
+
+    fn link(&self, text: &str, anchor: &str) -> String {
+        format!("[{}](#{})", text, anchor)
+    }
+}
+
+struct Html;
+
+impl Format for Html {
+    fn title(&self, res: &mut String, section: &Section, top: bool) {
+        let level = if top { "h1" } else { "h2" };
+        write!(
+            res,
+            "<{} id=\"{}\">{}</{}>\n",
+            level,
+            section.anchor(),
+            html_escape(&section.title()),
+            level
+        )
+        .unwrap();
+    }
+
+    fn paragraph(&self, res: &mut String, text: &str) {
+        write!(res, "<p>{}</p>\n", html_escape(text)).unwrap();
+    }
+
+    fn usage(&self, res: &mut String, usage: &str) {
+        write!(res, "<pre>{}</pre>\n", html_escape(usage)).unwrap();
+    }
+
+    fn table_title(&self, res: &mut String, title: &str) {
+        write!(res, "<h3>{}</h3>\n", html_escape(title)).unwrap();
+    }
+
+    fn table_start(&self, res: &mut String) {
+        res.push_str("<table>\n");
+    }
+
+    fn table_end(&self, res: &mut String) {
+        res.push_str("</table>\n");
+    }
+
+    fn group_row(&self, res: &mut String, help: &str) {
+        let help = html_escape(help).replace('\n', "<br>");
+        write!(res, "<tr><td colspan=\"2\"><b>{}</b></td></tr>\n", help).unwrap();
+    }
+
+    fn row(&self, res: &mut String, name: &str, help: &str) {
+        let help = html_escape(help).replace('\n', "<br>");
+        write!(res, "<tr><td>{}</td><td>{}</td></tr>\n", name, help).unwrap();
+    }
+
+    fn code(&self, text: &str) -> String {
+        format!("<code>{}</code>", html_escape(text))
+    }
+
+    fn link(&self, text: &str, anchor: &str) -> String {
+        format!("<a href=\"#{}\">{}</a>", anchor, text)
+    }
+}
+
+/// Table rows, commands link to their sections and group headers are bold
+fn rows<F: Format>(fmt: &F, res: &mut String, path: &[&str], items: &[Item]) {
+    for item in items {
+        match item.kind {
+            ItemKind::Decor => {
+                if let Some(help) = &item.help {
+                    fmt.group_row(res, help);
+                }
+            }
+            ItemKind::Command => {
+                let name = match item.long {
+                    Some(name) => {
+                        let mut path = path.to_vec();
+                        path.push(name);
+                        let mut res = fmt.link(&fmt.code(name), &anchor(&path));
+                        for alias in &item.aliases {
+                            write!(res, ", {}", fmt.code(alias)).unwrap();
+                        }
+                        res
+                    }
+                    None => fmt.code("COMMAND ..."),
+                };
+                fmt.row(res, &name, item.help.as_deref().unwrap_or(""));
+            }
+            ItemKind::Flag | ItemKind::Positional => {
+                let (name, help) = item_name(item);
+                fmt.row(res, &fmt.code(&name), &help);
+            }
+        }
+    }
+}
+
+/// Documentation for all the sections in a given format
+fn render<F: Format>(fmt: &F, sections: &[Section]) -> String {
+    let mut res = String::new();
+    for (ix, section) in sections.iter().enumerate() {
+        fmt.title(&mut res, section, ix == 0);
+        if let Some(descr) = section.info.descr {
+            fmt.paragraph(&mut res, descr);
+        }
+        fmt.usage(&mut res, &section.usage());
+        if let Some(header) = section.info.header {
+            fmt.paragraph(&mut res, header);
+        }
+
+        for group in section.info.help_sections(&section.meta()) {
+            for (title, items) in group.tables() {
+                fmt.table_title(&mut res, title);
+                if !items.is_empty() {
+                    fmt.table_start(&mut res);
+                    rows(fmt, &mut res, &section.path, &items);
+                    fmt.table_end(&mut res);
+                }
+            }
+        }
+
+        if let Some(footer) = section.info.footer {
+            fmt.paragraph(&mut res, footer);
+        }
+    }
+    res
+}
+
+impl<T> OptionParser<T> {
+    /// Parser and every command in it, recursively
+    pub(crate) fn sections<'a>(&self, name: &'a str) -> Vec<Section<'a>> {
+        let mut res = Vec::new();
+        collect_sections(
+            vec![name],
+            &self.info,
+            &self.parser_meta,
+            &self.help_meta,
+            &mut res,
+        );
+        res
+    }
+
+    /// Render reference documentation in Markdown format
+    ///
+    /// Documentation contains a section for the parser and for every command, recursively.
+    /// Each section contains description, usage, header, tables with positional items, options,
+    /// commands and [help sections](crate::Parser::help_section) and footer. Commands link to
+    /// their sections, `name` is used as the application name in usage lines and section titles.
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// let parser = Info::default()
+    ///     .descr("Does nothing")
+    ///     .for_parser(short('v').long("verbose").help("Be verbose").switch());
+    /// let md = parser.render_markdown("app");
+    /// assert!(md.starts_with("# app\n\nDoes nothing\n"));
+    /// assert!(md.contains("| `-v, --verbose` | Be verbose |"));
+    /// ```
+    #[must_use]
+    pub fn render_markdown(&self, name: &str) -> String {
+        render(&Markdown, &self.sections(name))
+    }
+
+    /// Render reference documentation in HTML format
+    ///
+    /// Contents are the same as with [`render_markdown`](OptionParser::render_markdown),
+    /// result is an HTML fragment without `<html>` and `<body>` tags so it can be embedded
+    /// into an existing page.
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// let parser = Info::default().for_parser(long("name").argument("NAME"));
+    /// let html = parser.render_html("app");
+    /// assert!(html.contains("<code>--name &lt;NAME&gt;</code>"));
+    /// ```
+    #[must_use]
+    pub fn render_html(&self, name: &str) -> String {
+        render(&Html, &self.sections(name))
+    }
+}
@@ -1,17 +1,25 @@
 //! Help message generation and rendering
 
 #![allow(clippy::write_with_newline)]
-use std::rc::Rc;
+use crate::Rc;
+use std::ffi::OsString;
 
-use crate::{args::Args, params::short, DynParse, Parser};
+use crate::{
+    args::{Arg, Args},
+    params::{long, positional, short, Named},
+    DynParse, Parser,
+};
 
 /// Unsuccessful command line parsing outcome, internal representation
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// Terminate and print this to stdout
     Stdout(String),
     /// Terminate and print this to stderr
     Stderr(String),
+    /// Terminate and print this to stderr along with the context of the failure
+    Context(Box<ErrorContext>),
     /// Expected one of those values
     ///
     /// Used internally to generate better error messages
@@ -29,8 +37,8 @@ impl Error {
             (_, b @ Error::Stdout(_)) => b,
 
             // parsing failure takes priority
-            (a @ Error::Stderr(_), _) => a,
-            (_, b @ Error::Stderr(_)) => b,
+            (a @ (Error::Stderr(_) | Error::Context(_)), _) => a,
+            (_, b @ (Error::Stderr(_) | Error::Context(_))) => b,
 
             // missing elements are combined
             (Error::Missing(mut a), Error::Missing(mut b)) => {
@@ -41,6 +49,57 @@ impl Error {
     }
 }
 
+/// Parsing failure along with the place it happened at
+#[doc(hidden)]
+#[derive(Clone, Debug)]
+pub struct ErrorContext {
+    /// Error message
+    pub message: String,
+    /// Original command line arguments and the index of the one that caused the failure
+    pub position: Option<(Rc<[OsString]>, usize)>,
+    /// Usage line of the innermost command the failure happened in
+    pub usage: Option<String>,
+    /// Help flag of that command
+    pub help_flag: Option<String>,
+    /// Failure is caused by missing items, `run_inner` keeps a hint about the help flag for those
+    pub missing: bool,
+}
+
+impl ErrorContext {
+    /// Error message followed by the command line with the failing argument marked, usage
+    /// line and a hint about the help flag
+    pub(crate) fn render(&self) -> String {
+        use std::fmt::Write;
+        let mut res = self.message.clone();
+        if let Some((argv, ix)) = &self.position {
+            let mut line = String::new();
+            let mut marker = String::new();
+            for (i, arg) in argv.iter().enumerate() {
+                if i > 0 {
+                    line.push(' ');
+                }
+                let arg = arg.to_string_lossy();
+                if i == *ix {
+                    let start = line.chars().count();
+                    let width = arg.chars().count().max(1);
+                    marker = format!("{:start$}{}", "", "^".repeat(width), start = start);
+                }
+                line.push_str(&arg);
+            }
+            if !marker.is_empty() {
+                write!(res, "\n    {}\n    {}", line, marker).unwrap();
+            }
+        }
+        if let Some(usage) = &self.usage {
+            write!(res, "\n{}", usage).unwrap();
+        }
+        if let Some(help) = &self.help_flag {
+            write!(res, "\nPass {} for usage information", help).unwrap();
+        }
+        res
+    }
+}
+
 #[doc(hidden)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum ItemKind {
@@ -58,6 +117,29 @@ pub struct Item {
     pub metavar: Option<&'static str>,
     pub help: Option<String>,
     pub kind: ItemKind,
+    /// visible aliases, used by commands
+    pub aliases: Vec<&'static str>,
+    /// hidden aliases: long names for flags and arguments, names for commands
+    pub hidden_aliases: Vec<&'static str>,
+    /// hidden short aliases for flags and arguments
+    pub short_aliases: Vec<char>,
+    /// inner parser for commands
+    pub subparser: Option<Rc<SubparserMeta>>,
+    /// rendered default value, see `fallback_display`
+    pub default: Option<String>,
+    /// detailed help message shown with `--help`, see `Named::long_help`
+    pub long_help: Option<String>,
+    /// help section, see `Parser::help_section`
+    pub section: Option<&'static str>,
+}
+
+/// Meta information about inner parser of a command
+#[doc(hidden)]
+#[derive(Clone, Debug)]
+pub struct SubparserMeta {
+    pub parser_meta: Meta,
+    pub help_meta: Meta,
+    pub info: Info,
 }
 
 impl std::fmt::Display for Item {
@@ -72,10 +154,7 @@ impl std::fmt::Display for Item {
             },
 
             ItemKind::Command => write!(f, "COMMAND ..."),
-            ItemKind::Positional => match self.metavar {
-                Some(m) => write!(f, "<{}>", m),
-                None => write!(f, "<FILE>"),
-            },
+            ItemKind::Positional => write!(f, "<{}>", self.positional_metavar()),
             ItemKind::Decor => Ok(()),
         }
     }
@@ -119,6 +198,47 @@ impl Item {
             metavar: None,
             help: help.map(Into::into),
             kind: ItemKind::Decor,
+            aliases: Vec::new(),
+            hidden_aliases: Vec::new(),
+            short_aliases: Vec::new(),
+            subparser: None,
+            default: None,
+            long_help: None,
+            section: None,
+        }
+    }
+
+    /// Item with the detailed help message if there is one
+    #[must_use]
+    pub fn long_form(self) -> Self {
+        match self.long_help {
+            Some(long_help) => Item {
+                help: Some(long_help),
+                long_help: None,
+                ..self
+            },
+            None => self,
+        }
+    }
+
+    /// Metavar of a positional item, `FILE` if it doesn't have one
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn positional_metavar(&self) -> &'static str {
+        match self.metavar {
+            Some(m) => m,
+            None => "FILE",
+        }
+    }
+
+    /// help message followed by the default value, if any
+    #[doc(hidden)]
+    #[must_use]
+    pub fn help_with_default(&self) -> Option<String> {
+        match (&self.help, &self.default) {
+            (help, None) => help.clone(),
+            (None, Some(d)) => Some(format!("[default: {}]", d)),
+            (Some(help), Some(d)) => Some(format!("{} [default: {}]", help, d)),
         }
     }
 
@@ -131,6 +251,15 @@ impl Item {
         }
     }
 
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn is_positional(&self) -> bool {
+        match self.kind {
+            ItemKind::Positional => true,
+            ItemKind::Flag | ItemKind::Decor | ItemKind::Command => false,
+        }
+    }
+
     #[doc(hidden)]
     #[must_use]
     pub const fn is_flag(&self) -> bool {
@@ -147,7 +276,6 @@ pub enum Meta {
     /// always fails
     Empty,
 
-    ///
     And(Vec<Meta>),
     Or(Vec<Meta>),
     Required(Box<Meta>),
@@ -155,6 +283,8 @@ pub enum Meta {
     Item(Item),
     Many(Box<Meta>),
     Decorated(Box<Meta>, String),
+    /// Parser is accepted but not shown in the help
+    Hidden(Box<Meta>),
     Id,
 }
 
@@ -164,9 +294,13 @@ impl Meta {
     pub fn is_required(&self) -> bool {
         match self {
             Meta::And(xs) => xs.iter().any(Meta::is_required),
-            Meta::Or(xs) => xs.iter().all(Meta::is_required),
+            // hidden branches don't count, if all of them are hidden there's nothing to require
+            Meta::Or(xs) => {
+                let visible = xs.iter().filter(|x| !x.is_hidden()).collect::<Vec<_>>();
+                !visible.is_empty() && visible.into_iter().all(Meta::is_required)
+            }
             Meta::Required(_) => true,
-            Meta::Empty | Meta::Optional(_) | Meta::Many(_) | Meta::Id => false,
+            Meta::Empty | Meta::Optional(_) | Meta::Many(_) | Meta::Hidden(_) | Meta::Id => false,
             Meta::Item(i) => match i.kind {
                 ItemKind::Command => true,
                 ItemKind::Decor => false,
@@ -228,6 +362,7 @@ impl Meta {
             | Meta::Item(_)
             | Meta::Many(_)
             | Meta::Decorated(..)
+            | Meta::Hidden(_)
             | Meta::Id) => Meta::Optional(Box::new(m)),
         }
     }
@@ -254,6 +389,12 @@ impl Meta {
         res
     }
 
+    pub fn positionals(&self) -> Vec<Item> {
+        let mut res = Vec::new();
+        self.collect_items(&mut res, Item::is_positional);
+        res
+    }
+
     #[must_use]
     pub fn decorate<M>(self, msg: M) -> Self
     where
@@ -262,10 +403,42 @@ impl Meta {
         Meta::Decorated(Box::new(self), msg.into())
     }
 
+    #[must_use]
+    pub fn map_items<F>(self, f: &F) -> Self
+    where
+        F: Fn(Item) -> Item,
+    {
+        let go = |xs: Vec<Meta>| xs.into_iter().map(|x| x.map_items(f)).collect();
+        match self {
+            Meta::And(xs) => Meta::And(go(xs)),
+            Meta::Or(xs) => Meta::Or(go(xs)),
+            Meta::Required(x) => Meta::Required(Box::new(x.map_items(f))),
+            Meta::Optional(x) => Meta::Optional(Box::new(x.map_items(f))),
+            Meta::Many(x) => Meta::Many(Box::new(x.map_items(f))),
+            Meta::Decorated(x, msg) => Meta::Decorated(Box::new(x.map_items(f)), msg),
+            Meta::Hidden(x) => Meta::Hidden(Box::new(x.map_items(f))),
+            Meta::Item(item) => Meta::Item(f(item)),
+            m @ (Meta::Empty | Meta::Id) => m,
+        }
+    }
+
+    /// Hidden parser or a combination of hidden parsers
+    #[must_use]
+    pub fn is_hidden(&self) -> bool {
+        match self {
+            Meta::Hidden(_) => true,
+            Meta::And(xs) | Meta::Or(xs) => !xs.is_empty() && xs.iter().all(Meta::is_hidden),
+            Meta::Required(x) | Meta::Optional(x) | Meta::Many(x) | Meta::Decorated(x, _) => {
+                x.is_hidden()
+            }
+            Meta::Empty | Meta::Id | Meta::Item(_) => false,
+        }
+    }
+
     #[must_use]
     pub fn is_empty(&self) -> bool {
         match self {
-            Meta::Empty | Meta::Id => true,
+            Meta::Empty | Meta::Id | Meta::Hidden(_) => true,
             Meta::And(xs) | Meta::Or(xs) => xs.iter().all(Meta::is_empty),
             Meta::Required(x) | Meta::Optional(x) | Meta::Many(x) | Meta::Decorated(x, _) => {
                 x.is_empty()
@@ -274,12 +447,45 @@ impl Meta {
         }
     }
 
+    /// Parser can succeed without consuming anything
+    fn accepts_nothing(&self) -> bool {
+        match self {
+            Meta::Id | Meta::Optional(_) | Meta::Many(_) => true,
+            Meta::Empty | Meta::Item(_) => false,
+            Meta::And(xs) => xs.iter().all(Meta::accepts_nothing),
+            Meta::Or(xs) => xs.iter().any(Meta::accepts_nothing),
+            Meta::Required(x) | Meta::Decorated(x, _) | Meta::Hidden(x) => x.accepts_nothing(),
+        }
+    }
+
+    /// Look for [`many`](Parser::many) around parsers that can succeed without consuming
+    /// anything, including parsers of nested commands
+    pub(crate) fn check_many(&self) -> Result<(), String> {
+        match self {
+            Meta::Many(x) if x.accepts_nothing() => Err(format!(
+                "many can't be used with a parser that succeeds without consuming anything: {}",
+                x
+            )),
+            Meta::Empty | Meta::Id => Ok(()),
+            Meta::And(xs) | Meta::Or(xs) => xs.iter().try_for_each(Meta::check_many),
+            Meta::Required(x)
+            | Meta::Optional(x)
+            | Meta::Many(x)
+            | Meta::Decorated(x, _)
+            | Meta::Hidden(x) => x.check_many(),
+            Meta::Item(item) => match &item.subparser {
+                Some(sub) => sub.parser_meta.check_many(),
+                None => Ok(()),
+            },
+        }
+    }
+
     fn collect_items<F>(&self, res: &mut Vec<Item>, pred: F)
     where
         F: Fn(&Item) -> bool + Copy,
     {
         match self {
-            Meta::Empty | Meta::Id => {}
+            Meta::Empty | Meta::Id | Meta::Hidden(_) => {}
             Meta::And(xs) | Meta::Or(xs) => {
                 for x in xs {
                     x.collect_items(res, pred);
@@ -297,16 +503,88 @@ impl Meta {
                 x.collect_items(res, pred);
                 if res.len() == prev_len {
                     res.pop();
+                } else if res.len() == prev_len + 1 && res[prev_len].help.is_none() {
+                    // help for a group of one item without its own help is the help for the item
+                    let mut item = res.pop().expect("just checked");
+                    item.help = Some(msg.clone());
+                    res.pop();
+                    res.push(item);
                 } else {
-                    res.push(Item::decoration(None::<String>));
+                    // decorations go into the same help sections as the items they surround
+                    res[prev_len - 1].section = res[prev_len].section;
+                    let mut end = Item::decoration(None::<String>);
+                    end.section = res[res.len() - 1].section;
+                    res.push(end);
                 }
             }
         }
     }
 
+    /// Contains only flags and arguments, no positional items or commands
+    fn only_flags(&self) -> bool {
+        match self {
+            Meta::Empty | Meta::Id | Meta::Hidden(_) => true,
+            Meta::And(xs) | Meta::Or(xs) => xs.iter().all(Meta::only_flags),
+            Meta::Required(x) | Meta::Optional(x) | Meta::Many(x) | Meta::Decorated(x, _) => {
+                x.only_flags()
+            }
+            Meta::Item(i) => i.is_flag(),
+        }
+    }
+
+    /// Usage without optional flags and arguments, `true` if anything was removed
+    fn without_optional_flags(self) -> (Meta, bool) {
+        match self {
+            Meta::Optional(x) | Meta::Many(x) if x.only_flags() => (Meta::Id, true),
+            m @ Meta::Or(_) if !m.is_required() && m.only_flags() => (Meta::Id, true),
+            Meta::And(xs) => {
+                let mut removed = false;
+                let xs = xs
+                    .into_iter()
+                    .filter_map(|x| {
+                        let (x, r) = x.without_optional_flags();
+                        removed |= r;
+                        (!matches!(x, Meta::Id)).then_some(x)
+                    })
+                    .collect::<Vec<_>>();
+                let meta = if xs.is_empty() {
+                    Meta::Id
+                } else {
+                    Meta::And(xs)
+                };
+                (meta, removed)
+            }
+            Meta::Required(x) if !matches!(*x, Meta::Many(_) | Meta::Optional(_)) => {
+                let (x, removed) = x.without_optional_flags();
+                (Meta::Required(Box::new(x)), removed)
+            }
+            Meta::Decorated(x, msg) => {
+                let (x, removed) = x.without_optional_flags();
+                (Meta::Decorated(Box::new(x), msg), removed)
+            }
+            m => (m, false),
+        }
+    }
+
+    /// Parts of the usage line that can go on separate lines
+    fn usage_chunks(&self) -> Vec<String> {
+        match self {
+            Meta::And(xs) => xs
+                .iter()
+                .filter(|x| !x.is_empty())
+                .flat_map(Meta::usage_chunks)
+                .collect(),
+            Meta::Decorated(x, _) => x.usage_chunks(),
+            m => Some(m.to_string())
+                .filter(|s| !s.is_empty())
+                .into_iter()
+                .collect(),
+        }
+    }
+
     fn is_simple(&self) -> bool {
         match self {
-            Meta::Empty | Meta::Id | Meta::Item(_) => true,
+            Meta::Empty | Meta::Id | Meta::Hidden(_) | Meta::Item(_) => true,
             Meta::And(_) | Meta::Or(_) => false,
             Meta::Required(m) | Meta::Optional(m) | Meta::Many(m) | Meta::Decorated(m, _) => {
                 m.is_simple()
@@ -315,22 +593,25 @@ impl Meta {
     }
 }
 
+/// Default command is an optional command item
+fn default_command(meta: &Meta) -> Option<&Item> {
+    match meta {
+        Meta::Optional(m) => match &**m {
+            Meta::Item(item) if item.is_command() => Some(item),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 fn dedup(prev: &mut Option<Item>, cur: &Meta) -> bool {
-    let item = if let Meta::Item(item) = cur {
-        item
-    } else {
-        return true;
+    let item = match (cur, default_command(cur)) {
+        (Meta::Item(item), _) | (_, Some(item)) => item,
+        _ => return true,
     };
     match prev {
-        Some(p) => {
-            if p.kind == ItemKind::Command && item.kind == ItemKind::Command {
-                false
-            } else {
-                *prev = Some(item.clone());
-                true
-            }
-        }
-        None => {
+        Some(p) if p.kind == ItemKind::Command && item.kind == ItemKind::Command => false,
+        _ => {
             *prev = Some(item.clone());
             true
         }
@@ -342,9 +623,10 @@ impl std::fmt::Display for Meta {
         match self {
             Meta::Empty => Ok(()),
             Meta::And(xs) => {
+                let xs = xs.iter().filter(|x| !x.is_empty()).collect::<Vec<_>>();
                 for (ix, x) in xs.iter().enumerate() {
                     write!(f, "{}", x)?;
-                    if ix + 1 < xs.len() && !x.is_empty() {
+                    if ix + 1 < xs.len() {
                         write!(f, " ")?;
                     }
                 }
@@ -354,8 +636,11 @@ impl std::fmt::Display for Meta {
                 let mut prev = None;
                 let xs = xs
                     .iter()
-                    .filter(|i| dedup(&mut prev, i))
+                    .filter(|i| !i.is_hidden() && dedup(&mut prev, i))
                     .collect::<Vec<_>>();
+                if xs.is_empty() {
+                    return Ok(());
+                }
                 let required = self.is_required();
                 if !required {
                     write!(f, "[")?;
@@ -365,7 +650,16 @@ impl std::fmt::Display for Meta {
                     // no arguments left, nothing to print here
                 }
                 for (ix, x) in xs.iter().enumerate() {
-                    write!(f, "{}", x)?;
+                    match (x, default_command(x)) {
+                        // already inside of the brackets, a default command makes all the
+                        // commands optional
+                        (Meta::Item(item), _) | (_, Some(item))
+                            if !required && item.is_command() =>
+                        {
+                            write!(f, "COMMAND")?;
+                        }
+                        _ => write!(f, "{}", x)?,
+                    }
                     if ix + 1 < xs.len() {
                         write!(f, " | ")?;
                     }
@@ -381,10 +675,11 @@ impl std::fmt::Display for Meta {
             }
             Meta::Required(m) if m.is_simple() => write!(f, "{}", m),
             Meta::Required(m) => write!(f, "({})", m),
+            Meta::Optional(_) if default_command(self).is_some() => write!(f, "[COMMAND]"),
             Meta::Optional(m) => write!(f, "[{}]", m),
             Meta::Many(m) => write!(f, "{}...", m),
             Meta::Item(i) => write!(f, "{}", i),
-            Meta::Id => Ok(()),
+            Meta::Id | Meta::Hidden(_) => Ok(()),
             Meta::Decorated(x, _) => write!(f, "{}", x),
         }
     }
@@ -396,10 +691,207 @@ impl From<Item> for Meta {
     }
 }
 
+/// Items shown in the help under a single heading
+pub(crate) struct HelpSection {
+    /// Section name, `None` for items without a section
+    pub(crate) title: Option<&'static str>,
+    pub(crate) positionals: Vec<Item>,
+    pub(crate) flags: Vec<Item>,
+    pub(crate) commands: Vec<Item>,
+}
+
+impl HelpSection {
+    const fn new(title: Option<&'static str>) -> Self {
+        Self {
+            title,
+            positionals: Vec::new(),
+            flags: Vec::new(),
+            commands: Vec::new(),
+        }
+    }
+
+    /// Tables for generated documentation: title and items, items without a section are
+    /// split into positional items, options and commands
+    pub(crate) fn tables(self) -> Vec<(&'static str, Vec<Item>)> {
+        match self.title {
+            Some(title) => vec![(
+                title,
+                [self.positionals, self.flags, self.commands].concat(),
+            )],
+            None => [
+                ("Positional items", self.positionals),
+                ("Options", self.flags),
+                ("Commands", self.commands),
+            ]
+            .into_iter()
+            .filter(|(_, items)| !items.is_empty())
+            .collect(),
+        }
+    }
+
+    /// Section has no items other than decorations
+    fn is_empty(&self) -> bool {
+        self.positionals
+            .iter()
+            .chain(&self.flags)
+            .chain(&self.commands)
+            .all(|i| i.kind == ItemKind::Decor)
+    }
+}
+
+/// Group help is a heading above the items of the group, end of the group is an empty line
+fn write_decoration(res: &mut String, item: &Item, last: bool) -> std::fmt::Result {
+    use std::fmt::Write;
+    match &item.help {
+        Some(help) => {
+            for line in help.split('\n') {
+                write!(res, "  {}\n", line)?;
+            }
+        }
+        // end of a decorated group is not needed at the end of the section
+        None if last => {}
+        None => write!(res, "\n")?,
+    }
+    Ok(())
+}
+
+fn write_positionals(res: &mut String, positionals: &[Item]) -> std::fmt::Result {
+    use std::fmt::Write;
+    let max_positional_width = positionals
+        .iter()
+        .filter_map(|i| i.metavar.map(|m| m.len() + 2))
+        .max()
+        .unwrap_or(0);
+    for (ix, i) in positionals.iter().enumerate() {
+        if i.kind == ItemKind::Decor {
+            write_decoration(res, i, ix + 1 == positionals.len())?;
+            continue;
+        }
+        match i.metavar {
+            Some(m) => write!(
+                res,
+                "    <{}>{:ident$}",
+                m,
+                "",
+                ident = max_positional_width - m.len()
+            )?,
+            None => write!(res, "    {:ident$}", "", ident = max_positional_width + 2)?,
+        }
+        match &i.help_with_default() {
+            Some(h) => {
+                for (ix, line) in h.split('\n').enumerate() {
+                    if ix == 0 || line.is_empty() {
+                        write!(res, "{}\n", line)?;
+                    } else {
+                        let ident = max_positional_width + 6;
+                        write!(res, "{:ident$}{}\n", "", line, ident = ident)?;
+                    }
+                }
+            }
+            None => {
+                // strip unnecessary spaces inserted by previous writes
+                res.truncate(res.trim_end_matches(' ').len());
+                write!(res, "\n")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_flags(res: &mut String, flags: &[Item]) -> std::fmt::Result {
+    use std::fmt::Write;
+    let max_name_width = flags.iter().map(Item::name_len).max().unwrap_or(0);
+    for (ix, i) in flags.iter().enumerate() {
+        if i.kind == ItemKind::Decor {
+            write_decoration(res, i, ix + 1 == flags.len())?;
+            continue;
+        }
+        match i.short {
+            Some(c) => write!(res, "    -{}", c)?,
+            None => write!(res, "      ")?,
+        }
+        if i.short.is_some() && i.long.is_some() {
+            write!(res, ", ")?;
+        } else {
+            write!(res, "  ")?;
+        }
+        match (i.long, i.metavar) {
+            (None, None) => write!(res, "{:ident$}", "", ident = max_name_width + 2)?,
+            (None, Some(m)) => write!(
+                res,
+                "<{}>{:ident$}",
+                m,
+                "",
+                ident = max_name_width - m.len()
+            )?,
+            (Some(l), None) => write!(res, "--{:ident$}", l, ident = max_name_width)?,
+            (Some(l), Some(m)) => write!(
+                res,
+                "--{:ident$}",
+                format!("{} <{}>", l, m),
+                ident = max_name_width
+            )?,
+        }
+        match &i.help_with_default() {
+            Some(h) => {
+                for (ix, line) in h.split('\n').enumerate() {
+                    if ix == 0 || line.is_empty() {
+                        write!(res, "{}\n", line)?;
+                    } else {
+                        write!(res, "{:ident$}{}\n", "", line, ident = max_name_width + 10)?;
+                    }
+                }
+            }
+            None => {
+                // strip unnecessary spaces inserted by previous writes
+                res.truncate(res.trim_end_matches(' ').len());
+                write!(res, "\n")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_commands(res: &mut String, commands: &[Item]) -> std::fmt::Result {
+    use std::fmt::Write;
+    let command_names = commands
+        .iter()
+        .map(|i| match i.long {
+            Some(_) => i
+                .long
+                .iter()
+                .chain(&i.aliases)
+                .copied()
+                .collect::<Vec<_>>()
+                .join(", "),
+            // external commands don't have a name
+            None => String::from("COMMAND ..."),
+        })
+        .collect::<Vec<_>>();
+    let max_command_width = command_names.iter().map(String::len).max().unwrap_or(0);
+    for (c, name) in commands.iter().zip(command_names) {
+        write!(res, "    {:indent$}", name, indent = max_command_width)?;
+        match &c.help {
+            Some(help) => {
+                write!(res, "  {}\n", help)?;
+            }
+            None => {
+                // strip unnecessary spaces inserted by previous writes
+                res.truncate(res.trim_end_matches(' ').len());
+                writeln!(res)?;
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Parser with atteched meta information
 #[derive(Clone)]
 pub struct OptionParser<T> {
     pub(crate) parse: Rc<DynParse<T>>,
+    /// parser without help, version and the check for unexpected items, default command runs
+    /// it so the outer parser can take the rest
+    pub(crate) inner: Rc<DynParse<T>>,
     pub(crate) parser_meta: Meta,
     pub(crate) help_meta: Meta,
     pub(crate) info: Info,
@@ -410,7 +902,7 @@ impl<T> OptionParser<T> {
     pub fn render_help(&self) -> Result<String, std::fmt::Error> {
         self.info
             .clone()
-            .render_help(self.parser_meta.clone(), self.help_meta.clone())
+            .render_help(self.parser_meta.clone(), self.help_meta.clone(), None)
     }
 }
 
@@ -424,7 +916,7 @@ impl<T> OptionParser<T> {
 ///                .footer("Beware of the Leopard");
 /// # drop(info);
 /// ```
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct Info {
     /// version field, see [`version`][Info::version]
     pub version: Option<&'static str>,
@@ -436,6 +928,59 @@ pub struct Info {
     pub footer: Option<&'static str>,
     /// Custom usage field, see [`usage`][Info::usage]
     pub usage: Option<&'static str>,
+    /// Short and long names of the help flag, see [`help_flag`][Info::help_flag]
+    pub(crate) help_flag: (Option<char>, Option<&'static str>),
+    /// Short and long names of the version flag, see [`version_flag`][Info::version_flag]
+    pub(crate) version_flag: (Option<char>, Option<&'static str>),
+    /// Version message, see [`version_format`][Info::version_format]
+    pub(crate) version_format: &'static str,
+    /// Accept `help` command, see [`help_command`][Info::help_command]
+    pub(crate) help_command: bool,
+    /// Exit code for parsing errors, see [`error_code`][Info::error_code]
+    pub(crate) error_code: i32,
+    /// Order of help sections, see [`sections`][Info::sections]
+    pub(crate) sections: &'static [&'static str],
+    /// Show listed sections without items, see [`show_empty_sections`][Info::show_empty_sections]
+    pub(crate) show_empty_sections: bool,
+    /// Items shown in the usage line, see [`usage_style`][Info::usage_style]
+    pub(crate) usage_style: UsageStyle,
+    /// Wrap usage line at this width, see [`usage_width`][Info::usage_width]
+    pub(crate) usage_width: Option<usize>,
+}
+
+/// Items shown in the usage line, see [`Info::usage_style`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum UsageStyle {
+    /// Every visible item
+    Full,
+    /// Optional flags and arguments are replaced with a single `[OPTIONS]`, required flags,
+    /// positional items and commands are shown
+    Compact,
+    /// [`Full`](UsageStyle::Full) if it fits into the terminal, [`Compact`](UsageStyle::Compact)
+    /// otherwise, wrapped at terminal width. Width comes from [`usage_width`](Info::usage_width)
+    /// if set, `COLUMNS` environment variable or defaults to 80 characters
+    Auto,
+}
+
+impl Default for Info {
+    fn default() -> Self {
+        Self {
+            version: None,
+            descr: None,
+            header: None,
+            footer: None,
+            usage: None,
+            help_flag: (Some('h'), Some("help")),
+            version_flag: (Some('v'), Some("version")),
+            version_format: "Version: {version}",
+            help_command: false,
+            error_code: 1,
+            sections: &[],
+            show_empty_sections: false,
+            usage_style: UsageStyle::Full,
+            usage_width: None,
+        }
+    }
 }
 
 impl Info {
@@ -507,24 +1052,400 @@ impl Info {
         self
     }
 
+    /// Replace names of the help flag, `-h` and `--help` by default
+    ///
+    /// Flag without any names is disabled.
+    /// ```rust
+    /// # use bpaf::*;
+    /// // -h is used for --host, help is available as --help only
+    /// let info = Info::default().help_flag(None, Some("help"));
+    /// # drop(info);
+    /// ```
+    #[must_use]
+    pub const fn help_flag(mut self, short: Option<char>, long: Option<&'static str>) -> Self {
+        self.help_flag = (short, long);
+        self
+    }
+
+    /// Replace names of the version flag, `-v` and `--version` by default
+    ///
+    /// Flag without any names is disabled, version flag is only accepted if
+    /// [`version`](Info::version) is set.
+    /// ```rust
+    /// # use bpaf::*;
+    /// // -v is used for --verbose
+    /// let info = Info::default()
+    ///     .version("1.0")
+    ///     .version_flag(Some('V'), Some("version"));
+    /// # drop(info);
+    /// ```
+    #[must_use]
+    pub const fn version_flag(mut self, short: Option<char>, long: Option<&'static str>) -> Self {
+        self.version_flag = (short, long);
+        self
+    }
+
+    /// Replace the message printed by the version flag, `Version: {version}` by default
+    ///
+    /// `{version}` is replaced with the value set by [`version`](Info::version)
+    /// ```rust
+    /// # use bpaf::*;
+    /// let parser = Info::default()
+    ///     .version("1.0")
+    ///     .version_format("mytool {version}")
+    ///     .for_parser(Parser::pure(()));
+    /// let msg = parser.run_line("--version").unwrap_err().unwrap_stdout();
+    /// assert_eq!(msg, "mytool 1.0");
+    /// ```
+    #[must_use]
+    pub const fn version_format(mut self, format: &'static str) -> Self {
+        self.version_format = format;
+        self
+    }
+
+    /// Accept `help` command that prints help for the parser or for a command given after it
+    ///
+    /// `tool help check` prints the same message as `tool check --help`: detailed help added with
+    /// [`Named::long_help`](crate::Named::long_help) is shown, unlike with `tool check -h`.
+    /// The word `help` is treated as the command only if the parser itself doesn't accept it.
+    /// ```rust
+    /// # use bpaf::*;
+    /// let file = positional("FILE");
+    /// let check = command("check", Some("Check a file"), Info::default().for_parser(file));
+    /// let parser = Info::default().help_command().for_parser(check);
+    /// let help = parser.run_line("help check").unwrap_err().unwrap_stdout();
+    /// assert!(help.starts_with("Usage: <FILE>\n"));
+    /// ```
+    #[must_use]
+    pub const fn help_command(mut self) -> Self {
+        self.help_command = true;
+        self
+    }
+
+    /// Exit code for parsing errors such as missing or unexpected arguments, `1` by default
+    ///
+    /// Used by [`run`](OptionParser::run) and [`run_with`](OptionParser::run_with) of the top
+    /// level parser, many GNU tools use `2`.
+    /// ```rust
+    /// # use bpaf::*;
+    /// let info = Info::default().error_code(2);
+    /// # drop(info);
+    /// ```
+    #[must_use]
+    pub const fn error_code(mut self, code: i32) -> Self {
+        self.error_code = code;
+        self
+    }
+
+    /// Order of help sections created with [`help_section`](Parser::help_section)
+    ///
+    /// Sections listed here are shown in this order, other sections follow in order of
+    /// appearance. Items without a section are always shown first, sections without visible
+    /// items are not shown unless [`show_empty_sections`](Info::show_empty_sections) is set.
+    /// ```rust
+    /// # use bpaf::*;
+    /// let host = long("host").argument("HOST").help_section("Network options");
+    /// let output = long("output").argument("FILE").help_section("Output options");
+    /// let parser = Info::default()
+    ///     .sections(&["Output options", "Network options"])
+    ///     .for_parser(construct!(host, output));
+    /// let help = parser.render_help().unwrap();
+    /// let (output, network) = (help.find("Output options:"), help.find("Network options:"));
+    /// assert!(output < network);
+    /// ```
+    #[must_use]
+    pub const fn sections(mut self, sections: &'static [&'static str]) -> Self {
+        self.sections = sections;
+        self
+    }
+
+    /// Show help sections listed in [`sections`](Info::sections) even without visible items
+    ///
+    /// By default a section is not shown if all of its items are [hidden](Parser::hide) or
+    /// belong to nested commands, with this option its heading is shown anyway.
+    /// ```rust
+    /// # use bpaf::*;
+    /// let debug = long("trace").switch().help_section("Debug options").hide();
+    /// let parser = Info::default()
+    ///     .sections(&["Debug options"])
+    ///     .show_empty_sections()
+    ///     .for_parser(debug);
+    /// assert!(parser.render_help().unwrap().contains("Debug options:\n"));
+    /// ```
+    #[must_use]
+    pub const fn show_empty_sections(mut self) -> Self {
+        self.show_empty_sections = true;
+        self
+    }
+
+    /// Pick items shown in the generated usage line, all of them by default
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// let verbose = short('v').switch();
+    /// let jobs = short('j').argument("N").optional();
+    /// let file = positional("FILE");
+    /// let parser = Info::default()
+    ///     .usage_style(UsageStyle::Compact)
+    ///     .for_parser(construct!(verbose, jobs, file));
+    /// let help = parser.render_help().unwrap();
+    /// assert!(help.starts_with("Usage: [OPTIONS] <FILE>\n"));
+    /// ```
+    #[must_use]
+    pub const fn usage_style(mut self, style: UsageStyle) -> Self {
+        self.usage_style = style;
+        self
+    }
+
+    /// Wrap generated usage line so it fits into `width` characters
+    ///
+    /// Usage line is not wrapped by default unless [`UsageStyle::Auto`] is used, in which case
+    /// it wraps at terminal width taken from `COLUMNS` environment variable. Continuation lines
+    /// are aligned with the first item after the program name, items and groups of items are
+    /// never split.
+    /// ```rust
+    /// # use bpaf::*;
+    /// let a = short('a').argument("ARG");
+    /// let b = short('b').argument("ARG");
+    /// let c = short('c').argument("ARG");
+    /// let parser = Info::default().usage_width(20).for_parser(construct!(a, b, c));
+    /// let help = parser.render_help().unwrap();
+    /// assert!(help.starts_with("Usage: -a ARG -b ARG\n       -c ARG\n"));
+    /// ```
+    #[must_use]
+    pub const fn usage_width(mut self, width: usize) -> Self {
+        self.usage_width = Some(width);
+        self
+    }
+
+    /// Generated usage line without `Usage: ` prefix, `indent` is the width of that prefix
+    pub(crate) fn render_usage(
+        &self,
+        parser_meta: &Meta,
+        name: Option<&str>,
+        indent: usize,
+    ) -> String {
+        let full = parser_meta.usage_chunks();
+        let full_len =
+            full.iter().map(|c| c.len() + 1).sum::<usize>() + name.map_or(0, |n| n.len() + 1);
+        let width = match (self.usage_width, self.usage_style) {
+            (Some(width), _) => Some(width),
+            (None, UsageStyle::Auto) => Some(terminal_width()),
+            (None, UsageStyle::Full | UsageStyle::Compact) => None,
+        };
+        let compact = match (self.usage_style, width) {
+            (UsageStyle::Full, _) => false,
+            (UsageStyle::Compact, _) => true,
+            (UsageStyle::Auto, width) => width.is_some_and(|width| indent + full_len > width + 1),
+        };
+        let chunks = match parser_meta.clone().without_optional_flags() {
+            (meta, true) if compact => {
+                let mut chunks = vec![String::from("[OPTIONS]")];
+                chunks.extend(meta.usage_chunks());
+                chunks
+            }
+            _ => full,
+        };
+
+        let mut res = String::new();
+        let mut col = indent;
+        let hanging = indent + name.map_or(0, |n| n.len() + 1);
+        if let Some(name) = name {
+            res.push_str(name);
+            col += name.len();
+        }
+        for chunk in chunks {
+            if !res.is_empty() {
+                match width {
+                    Some(width) if col + 1 + chunk.len() > width && col > hanging => {
+                        res.push('\n');
+                        res.push_str(&" ".repeat(hanging));
+                        col = hanging;
+                    }
+                    _ => {
+                        res.push(' ');
+                        col += 1;
+                    }
+                }
+            }
+            res.push_str(&chunk);
+            col += chunk.len();
+        }
+        res
+    }
+
+    /// Attach usage line and help hint of this parser to a parsing failure unless a nested
+    /// command did it already
+    pub(crate) fn error_context(
+        &self,
+        parser_meta: &Meta,
+        name: Option<&str>,
+        err: Error,
+    ) -> Error {
+        let mut context = match err {
+            Error::Stderr(message) => Box::new(ErrorContext {
+                message,
+                position: None,
+                usage: None,
+                help_flag: None,
+                missing: false,
+            }),
+            Error::Missing(metas) => Box::new(ErrorContext {
+                message: format!("Expected {}", Meta::Or(metas)),
+                position: None,
+                usage: None,
+                help_flag: None,
+                missing: true,
+            }),
+            Error::Context(context) => context,
+            err @ Error::Stdout(_) => return err,
+        };
+        if context.usage.is_none() {
+            context.usage = Some(match self.usage {
+                Some(usage) => usage.to_owned(),
+                None => {
+                    let usage = self.render_usage(parser_meta, name, "Usage: ".len());
+                    format!("Usage: {}", usage).trim_end().to_owned()
+                }
+            });
+            context.help_flag = match self.help_flag {
+                (_, Some(long)) => Some(format!("--{}", long)),
+                (Some(short), None) => Some(format!("-{}", short)),
+                (None, None) => None,
+            };
+        }
+        Error::Context(context)
+    }
+
+    /// Items of the parser grouped by help sections, items without a section go first
+    pub(crate) fn help_sections(&self, meta: &Meta) -> Vec<HelpSection> {
+        let mut res = std::iter::once(None)
+            .chain(self.sections.iter().copied().map(Some))
+            .map(HelpSection::new)
+            .collect::<Vec<_>>();
+        let mut place = |items: Vec<Item>, pick: fn(&mut HelpSection) -> &mut Vec<Item>| {
+            for item in items {
+                let ix = match res.iter().position(|s| s.title == item.section) {
+                    Some(ix) => ix,
+                    None => {
+                        res.push(HelpSection::new(item.section));
+                        res.len() - 1
+                    }
+                };
+                pick(&mut res[ix]).push(item);
+            }
+        };
+        place(meta.positionals(), |s| &mut s.positionals);
+        place(meta.flags(), |s| &mut s.flags);
+        place(meta.commands(), |s| &mut s.commands);
+        if !self.show_empty_sections {
+            res.retain(|s| s.title.is_none() || !s.is_empty());
+        }
+        res
+    }
+
     fn help_parser(&self) -> Parser<ExtraParams> {
-        let help = short('h')
-            .long("help")
-            .help("Prints help information")
-            .req_flag(ExtraParams::Help);
-
-        match self.version {
-            Some(v) => help.or_else(
-                short('v')
-                    .long("version")
+        let help = flag_names(self.help_flag).map(|named| {
+            let flag = named.help("Prints help information").req_flag(());
+            // short name shows the short help, long name shows the detailed help
+            let parse = flag.parse;
+            let parse = move |args: Args| {
+                let (_, rest) = parse(args.clone())?;
+                let long = matches!(rest.removed_since(&args), Some(Arg::Long(_)));
+                Ok((ExtraParams::Help(long), rest))
+            };
+            Parser {
+                parse: Rc::new(parse),
+                meta: flag.meta,
+            }
+        });
+        let version = match self.version {
+            Some(v) => flag_names(self.version_flag).map(|named| {
+                named
                     .help("Prints version information")
-                    .req_flag(ExtraParams::Version(v)),
-            ),
-            None => help,
+                    .req_flag(ExtraParams::Version(v))
+            }),
+            None => None,
+        };
+        help.into_iter()
+            .chain(version)
+            .reduce(Parser::or_else)
+            .unwrap_or_else(|| Parser::fail(""))
+    }
+
+    /// Meta for help and version flags, along with the `help` command if enabled
+    ///
+    /// The `help` command is not parsed by [`Info::help_parser`], it is handled only after the
+    /// user's parser failed to consume the word `help`.
+    fn help_meta(&self) -> Meta {
+        let flags = self.help_parser().meta;
+        if !self.help_command {
+            return flags;
         }
+        let item = Item {
+            short: None,
+            long: Some("help"),
+            metavar: None,
+            help: Some(String::from("Prints help information for a command")),
+            kind: ItemKind::Command,
+            aliases: Vec::new(),
+            hidden_aliases: Vec::new(),
+            short_aliases: Vec::new(),
+            subparser: Some(Rc::new(SubparserMeta {
+                parser_meta: positional("COMMAND").many().meta,
+                help_meta: Meta::Empty,
+                info: Info::default().descr("Prints help information for a command"),
+            })),
+            default: None,
+            long_help: None,
+            section: None,
+        }
+        .required(false);
+        match flags {
+            Meta::Empty => item,
+            flags => Meta::Or(vec![flags, item]),
+        }
+    }
+
+    /// Help message for a command given by names of commands leading to it
+    fn command_help(
+        self,
+        parser_meta: Meta,
+        help_meta: Meta,
+        name: Option<&str>,
+        path: &[String],
+    ) -> Result<String, String> {
+        let (cmd, rest) = match path.split_first() {
+            Some(x) => x,
+            None => {
+                let parser_meta = parser_meta.map_items(&Item::long_form);
+                return self
+                    .render_help(parser_meta, help_meta, name)
+                    .map_err(|e| e.to_string());
+            }
+        };
+        let (cmd, sub) = parser_meta
+            .commands()
+            .into_iter()
+            .find(|i| i.long == Some(cmd.as_str()) || i.aliases.contains(&cmd.as_str()))
+            .and_then(|i| Some((i.long?, i.subparser?)))
+            .ok_or_else(|| format!("{} is not a known command", cmd))?;
+        let name = name.map(|n| format!("{} {}", n, cmd));
+        sub.info.clone().command_help(
+            sub.parser_meta.clone(),
+            sub.help_meta.clone(),
+            name.as_deref(),
+            rest,
+        )
     }
 
-    fn render_help(self, parser_meta: Meta, help_meta: Meta) -> Result<String, std::fmt::Error> {
+    /// Render help, `name` is a program name along with command names for the usage line
+    pub(crate) fn render_help(
+        self,
+        parser_meta: Meta,
+        help_meta: Meta,
+        name: Option<&str>,
+    ) -> Result<String, std::fmt::Error> {
         use std::fmt::Write;
         let mut res = String::new();
         if let Some(t) = self.descr {
@@ -533,7 +1454,8 @@ impl Info {
         if let Some(u) = self.usage {
             write!(res, "{}\n\n", u)?;
         } else {
-            write!(res, "Usage: {}", parser_meta)?;
+            let usage = self.render_usage(&parser_meta, name, "Usage: ".len());
+            write!(res, "Usage: {}", usage)?;
             // strip unnecessary spaces inserted by previous writes
             res.truncate(res.trim_end_matches(' ').len());
             write!(res, "\n")?;
@@ -542,90 +1464,63 @@ impl Info {
             write!(res, "\n{}\n", t)?;
         }
         let meta = Meta::and(parser_meta, help_meta);
-        let flags = &meta.flags();
-
-        let max_name_width = flags.iter().map(Item::name_len).max().unwrap_or(0);
-        if !flags.is_empty() {
-            write!(res, "\nAvailable options:\n")?;
-        }
-        for i in flags {
-            match i.short {
-                Some(c) => write!(res, "    -{}", c)?,
-                None => write!(res, "      ")?,
-            }
-            if i.short.is_some() && i.long.is_some() {
-                write!(res, ", ")?;
-            } else {
-                write!(res, "  ")?;
-            }
-            match (i.long, i.metavar) {
-                (None, None) => write!(res, "{:ident$}", "", ident = max_name_width + 2)?,
-                (None, Some(m)) => write!(
-                    res,
-                    "<{}>{:ident$}",
-                    m,
-                    "",
-                    ident = max_name_width - m.len()
-                )?,
-                (Some(l), None) => write!(res, "--{:ident$}", l, ident = max_name_width)?,
-                (Some(l), Some(m)) => write!(
-                    res,
-                    "--{:ident$}",
-                    format!("{} <{}>", l, m),
-                    ident = max_name_width
-                )?,
-            }
-            match &i.help {
-                Some(h) => {
-                    for (ix, line) in h.split('\n').enumerate() {
-                        if ix == 0 {
-                            write!(res, "{}\n", line)?;
-                        } else {
-                            write!(res, "{:ident$}{}\n", "", line, ident = max_name_width + 10)?;
-                        }
-                    }
-                }
+        for section in self.help_sections(&meta) {
+            match section.title {
                 None => {
-                    // strip unnecessary spaces inserted by previous writes
-                    res.truncate(res.trim_end_matches(' ').len());
-                    write!(res, "\n")?;
-                }
-            }
-        }
-
-        let commands = &meta.commands();
-        if !commands.is_empty() {
-            write!(res, "\nAvailable commands:\n")?;
-        }
-        let max_command_width = commands
-            .iter()
-            .map(|i| i.long.map_or(0, str::len))
-            .max()
-            .unwrap_or(0);
-        for c in commands {
-            if let Some(l) = c.long {
-                write!(res, "    {:indent$}", l, indent = max_command_width)?;
-            } else {
-                write!(res, "    {:indent$}", "", indent = max_command_width)?;
-            }
-            match &c.help {
-                Some(help) => {
-                    write!(res, "  {}\n", help)?;
+                    if !section.positionals.is_empty() {
+                        write!(res, "\nAvailable positional items:\n")?;
+                        write_positionals(&mut res, &section.positionals)?;
+                    }
+                    if !section.flags.is_empty() {
+                        write!(res, "\nAvailable options:\n")?;
+                        write_flags(&mut res, &section.flags)?;
+                    }
+                    if !section.commands.is_empty() {
+                        write!(res, "\nAvailable commands:\n")?;
+                        write_commands(&mut res, &section.commands)?;
+                    }
                 }
-                None => {
-                    // strip unnecessary spaces inserted by previous writes
-                    res.truncate(res.trim_end_matches(' ').len());
-                    writeln!(res)?;
+                Some(title) => {
+                    write!(res, "\n{}:\n", title)?;
+                    write_positionals(&mut res, &section.positionals)?;
+                    write_flags(&mut res, &section.flags)?;
+                    write_commands(&mut res, &section.commands)?;
                 }
             }
         }
-
         if let Some(t) = self.footer {
             write!(res, "\n{}\n", t)?;
         }
         Ok(res)
     }
 
+    /// Attach additional information to the parser, checking the parser for problems first
+    ///
+    /// Currently reports [`many`](Parser::many) and [`some`](Parser::some) used with parsers
+    /// that can succeed without consuming anything, such as [`optional`](Parser::optional)
+    /// or [`switch`](crate::params::Named::switch) parsers, here or in any nested command.
+    /// With [`for_parser`](Info::for_parser) such parsers fail at runtime instead.
+    ///
+    /// # Errors
+    /// Returns a message describing the problem
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// let verbose = short('v').switch().many();
+    /// let err = Info::default().try_for_parser(verbose).err().unwrap();
+    /// assert_eq!(
+    ///     err,
+    ///     "many can't be used with a parser that succeeds without consuming anything: [-v]"
+    /// );
+    /// ```
+    pub fn try_for_parser<T>(self, parser: Parser<T>) -> Result<OptionParser<T>, String>
+    where
+        T: 'static + Clone + std::fmt::Debug,
+    {
+        parser.meta.check_many()?;
+        Ok(self.for_parser(parser))
+    }
+
     /// Attach additional information to the parser
     #[must_use]
     pub fn for_parser<T>(self, parser: Parser<T>) -> OptionParser<T>
@@ -633,18 +1528,57 @@ impl Info {
         T: 'static + Clone + std::fmt::Debug,
     {
         let parser_meta = parser.meta.clone();
-        let help_meta = self.help_parser().meta;
+        let help_meta = self.help_meta();
+        let commands = parser_meta
+            .commands()
+            .into_iter()
+            .flat_map(|i| i.long.into_iter().chain(i.aliases))
+            .collect::<Rc<[_]>>();
+        let hidden_commands = parser_meta
+            .commands()
+            .into_iter()
+            .flat_map(|i| i.hidden_aliases)
+            .collect::<Rc<[_]>>();
+        let multicall = parser_meta
+            .commands()
+            .into_iter()
+            .filter_map(|i| {
+                let name = i.long?;
+                Some(
+                    std::iter::once(name)
+                        .chain(i.aliases)
+                        .chain(i.hidden_aliases)
+                        .collect(),
+                )
+            })
+            .collect::<Vec<_>>();
         let Parser {
             parse: p_parse,
             meta: p_meta,
         } = parser;
         let info = self.clone();
-        let p = move |args: Args| {
+        let inner = {
+            let (commands, hidden_commands) = (commands.clone(), hidden_commands.clone());
+            let p_parse = p_parse.clone();
+            move |mut args: Args| {
+                args.commands = commands.clone();
+                args.hidden_commands = hidden_commands.clone();
+                p_parse(args)
+            }
+        };
+        let p = move |mut args: Args| {
+            if let Some((msg, origin)) = args.error.take() {
+                let err = args.error_at_origin(origin, msg);
+                return Err(self.error_context(&p_meta, args.name.as_deref(), err));
+            }
+            args.commands = commands.clone();
+            args.hidden_commands = hidden_commands.clone();
+            args.select_command(&multicall);
             let err = match p_parse(args.clone()).and_then(check_unexpected) {
                 Ok(r) => return Ok(r),
 
                 // Stderr means
-                Err(Error::Stderr(e)) => Error::Stderr(e),
+                Err(err @ (Error::Stderr(_) | Error::Context(_))) => err,
 
                 // Stdout usually means a happy path such as calling --help or --version on one of
                 // the nested commands
@@ -652,23 +1586,53 @@ impl Info {
                 Err(err) => err,
             };
 
+            let name = args.name.clone();
+            // `help` command is only used if user's parser doesn't accept the word `help` itself
+            let mut help_args = args.clone();
+            if self.help_command && help_args.take_cmd("help") {
+                let mut path = Vec::new();
+                while let Ok(Some(word)) = help_args.take_positional_word() {
+                    path.push(word.utf8.unwrap_or_default());
+                }
+                let help = self.clone().command_help(
+                    p_meta.clone(),
+                    self.help_meta(),
+                    name.as_deref(),
+                    &path,
+                );
+                return Err(match help {
+                    Ok(msg) => Error::Stdout(msg),
+                    Err(msg) => self.error_context(&p_meta, name.as_deref(), Error::Stderr(msg)),
+                });
+            }
             match (self.help_parser().parse)(args) {
-                Ok((ExtraParams::Help, _)) => {
+                Ok((ExtraParams::Help(long), _)) => {
+                    let meta = if long {
+                        p_meta.clone().map_items(&Item::long_form)
+                    } else {
+                        p_meta.clone()
+                    };
                     let msg = self
                         .clone()
-                        .render_help(p_meta.clone(), self.help_parser().meta)
+                        .render_help(meta, self.help_meta(), name.as_deref())
                         .expect("Couldn't render help");
                     return Err(Error::Stdout(msg));
                 }
                 Ok((ExtraParams::Version(v), _)) => {
-                    return Err(Error::Stdout(format!("Version: {v}")));
+                    let msg = self.version_format.replace("{version}", v);
+                    return Err(Error::Stdout(msg));
                 }
                 Err(_) => {}
             }
-            Err(err)
+            // missing items are reported by the outer parser, it might have alternatives
+            Err(match err {
+                err @ Error::Missing(_) => err,
+                err => self.error_context(&p_meta, name.as_deref(), err),
+            })
         };
         OptionParser {
             parse: Rc::new(p),
+            inner: Rc::new(inner),
             info,
             parser_meta,
             help_meta,
@@ -676,19 +1640,38 @@ impl Info {
     }
 }
 
+/// Terminal width from `COLUMNS` environment variable, 80 if it's not set
+fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|cols| cols.parse().ok())
+        .filter(|cols| *cols > 0)
+        .unwrap_or(80)
+}
+
+/// Named flag with optional short and long names, `None` if both are missing
+fn flag_names((s, l): (Option<char>, Option<&'static str>)) -> Option<Named> {
+    match (s, l) {
+        (Some(s), Some(l)) => Some(short(s).long(l)),
+        (Some(s), None) => Some(short(s)),
+        (None, Some(l)) => Some(long(l)),
+        (None, None) => None,
+    }
+}
+
 #[doc(hidden)]
 #[derive(Clone, Debug)]
 pub enum ExtraParams {
-    Help,
+    /// `true` for the detailed help
+    Help(bool),
     Version(&'static str),
 }
 
 fn check_unexpected<T>((t, args): (T, Args)) -> Result<(T, Args), Error> {
-    match args.peek() {
+    match args.items_iter().next() {
         None => Ok((t, args)),
-        Some(item) => Err(Error::Stderr(format!(
-            "{} is not expected in this context",
-            item
-        ))),
+        Some((ix, item)) => {
+            Err(args.error_at(ix, format!("{} is not expected in this context", item)))
+        }
     }
 }
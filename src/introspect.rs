@@ -0,0 +1,555 @@
+//! Structure of a parser for documentation generators, linters and similar tools
+//!
+//! [`OptionParser::describe`] returns a [`ParserDoc`] with information from [`Info`] and a tree
+//! of [`Node`]s mirroring how the parser was composed. Commands contain [`ParserDoc`] of their
+//! inner parsers. Use [`Node::entries`] to get a flat list of items instead.
+//!
+//! ```rust
+//! # use bpaf::*;
+//! use bpaf::introspect::{ItemDoc, Occurrence};
+//! let verbose = short('v').long("verbose").help("Print more").switch();
+//! let file = Positional::new("FILE").help("File to process").string();
+//! let parser = Info::default()
+//!     .descr("Process a file")
+//!     .for_parser(construct!(verbose, file));
+//!
+//! let doc = parser.describe();
+//! assert_eq!(doc.descr.as_deref(), Some("Process a file"));
+//! for entry in doc.items.entries() {
+//!     match entry.item {
+//!         ItemDoc::Option(opt) => assert_eq!(opt.long, Some("verbose")),
+//!         ItemDoc::Positional(pos) => {
+//!             assert_eq!(pos.metavar, "FILE");
+//!             assert_eq!(entry.occurrence, Occurrence::Required);
+//!         }
+//!         _ => unreachable!(),
+//!     }
+//! }
+//! ```
+use crate::info::{Item, ItemKind, Meta, SubparserMeta};
+use crate::{Info, OptionParser, Parser};
+
+/// Information about [`OptionParser`], see [`OptionParser::describe`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ParserDoc {
+    /// See [`Info::descr`]
+    pub descr: Option<String>,
+    /// See [`Info::header`]
+    pub header: Option<String>,
+    /// See [`Info::footer`]
+    pub footer: Option<String>,
+    /// See [`Info::usage`]
+    pub usage: Option<String>,
+    /// See [`Info::version`]
+    pub version: Option<String>,
+    /// See [`Info::sections`]
+    pub sections: Vec<String>,
+    /// Items defined by the parser
+    pub items: Node,
+    /// Items added by `bpaf` itself: `--help` and `--version`
+    pub help_items: Node,
+}
+
+/// A part of a parser, see [`Parser::describe`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Node {
+    /// All of the nodes must succeed, for example created by [`construct!`](crate::construct)
+    ///
+    /// Empty list always succeeds
+    All(Vec<Node>),
+    /// One of the nodes must succeed, for example created by [`Parser::or_else`]
+    ///
+    /// Empty list always fails
+    Any(Vec<Node>),
+    /// Node is optional
+    Optional(Box<Node>),
+    /// Node can be repeated
+    Many(Box<Node>),
+    /// Nodes with a group help message, see [`Parser::group_help`]
+    Group(String, Box<Node>),
+    /// Node is accepted but not shown in the help, see [`Parser::hide`]
+    Hidden(Box<Node>),
+    /// A single item
+    Item(ItemDoc),
+}
+
+/// A single item of a parser
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ItemDoc {
+    /// A flag, a switch or an argument
+    Option(OptionDoc),
+    /// A positional item
+    Positional(PositionalDoc),
+    /// A command with an inner parser
+    Command(CommandDoc),
+    /// Unknown commands, see [`external_command`](crate::external_command)
+    ExternalCommand {
+        /// Help message
+        help: Option<String>,
+    },
+}
+
+/// A flag, a switch or an argument
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct OptionDoc {
+    /// Short name
+    pub short: Option<char>,
+    /// Long name
+    pub long: Option<&'static str>,
+    /// Short names accepted but not shown in the help
+    pub short_aliases: Vec<char>,
+    /// Long names accepted but not shown in the help
+    pub long_aliases: Vec<&'static str>,
+    /// Metavariable for arguments, `None` for flags and switches
+    pub metavar: Option<&'static str>,
+    /// Help message
+    pub help: Option<String>,
+    /// Default value, see [`Parser::fallback_display`]
+    pub default: Option<String>,
+    /// Help section, see [`Parser::help_section`]
+    pub section: Option<&'static str>,
+}
+
+/// A positional item
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PositionalDoc {
+    /// Metavariable
+    pub metavar: &'static str,
+    /// Help message
+    pub help: Option<String>,
+    /// Default value, see [`Parser::fallback_display`]
+    pub default: Option<String>,
+    /// Help section, see [`Parser::help_section`]
+    pub section: Option<&'static str>,
+}
+
+/// A command
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CommandDoc {
+    /// Command name
+    pub name: &'static str,
+    /// Names shown in the help along with the main name
+    pub aliases: Vec<&'static str>,
+    /// Names accepted but not shown in the help
+    pub hidden_aliases: Vec<&'static str>,
+    /// Help message
+    pub help: Option<String>,
+    /// Help section, see [`Parser::help_section`]
+    pub section: Option<&'static str>,
+    /// Inner parser
+    pub parser: Box<ParserDoc>,
+}
+
+/// How many times an item can be present
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Occurrence {
+    /// Exactly once
+    Required,
+    /// At most once
+    Optional,
+    /// Any number of times
+    Many,
+}
+
+/// A single item with its properties, see [`Node::entries`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Entry<'a> {
+    /// The item
+    pub item: &'a ItemDoc,
+    /// How many times it can be present
+    pub occurrence: Occurrence,
+    /// Is it hidden from the help
+    pub hidden: bool,
+    /// Closest group help message, if any
+    pub group: Option<&'a str>,
+}
+
+impl Node {
+    /// All the items in this node, depth first, without going into commands
+    ///
+    /// Items in [`Node::Any`] are optional unless there's only one of them.
+    #[must_use]
+    pub fn entries(&self) -> Vec<Entry<'_>> {
+        let mut res = Vec::new();
+        self.collect_entries(&mut res, Occurrence::Required, false, None);
+        res
+    }
+
+    fn collect_entries<'a>(
+        &'a self,
+        res: &mut Vec<Entry<'a>>,
+        occurrence: Occurrence,
+        hidden: bool,
+        group: Option<&'a str>,
+    ) {
+        let weaker = |o| match (occurrence, o) {
+            (Occurrence::Many, _) | (_, Occurrence::Many) => Occurrence::Many,
+            (Occurrence::Optional, _) | (_, Occurrence::Optional) => Occurrence::Optional,
+            (Occurrence::Required, Occurrence::Required) => Occurrence::Required,
+        };
+        match self {
+            Node::All(xs) => {
+                for x in xs {
+                    x.collect_entries(res, occurrence, hidden, group);
+                }
+            }
+            Node::Any(xs) => {
+                let occurrence = if xs.len() > 1 {
+                    weaker(Occurrence::Optional)
+                } else {
+                    occurrence
+                };
+                for x in xs {
+                    x.collect_entries(res, occurrence, hidden, group);
+                }
+            }
+            Node::Optional(x) => {
+                x.collect_entries(res, weaker(Occurrence::Optional), hidden, group)
+            }
+            Node::Many(x) => x.collect_entries(res, Occurrence::Many, hidden, group),
+            Node::Group(msg, x) => x.collect_entries(res, occurrence, hidden, Some(msg)),
+            Node::Hidden(x) => x.collect_entries(res, occurrence, true, group),
+            Node::Item(item) => res.push(Entry {
+                item,
+                occurrence,
+                hidden,
+                group,
+            }),
+        }
+    }
+}
+
+impl From<&Meta> for Node {
+    fn from(meta: &Meta) -> Self {
+        let go = |xs: &[Meta]| xs.iter().map(Node::from).collect();
+        match meta {
+            Meta::Empty => Node::Any(Vec::new()),
+            Meta::Id => Node::All(Vec::new()),
+            Meta::And(xs) => Node::All(go(xs)),
+            Meta::Or(xs) => Node::Any(go(xs)),
+            Meta::Required(x) => Node::from(&**x),
+            Meta::Optional(x) => Node::Optional(Box::new(Node::from(&**x))),
+            Meta::Many(x) => Node::Many(Box::new(Node::from(&**x))),
+            Meta::Decorated(x, msg) => Node::Group(msg.clone(), Box::new(Node::from(&**x))),
+            Meta::Hidden(x) => Node::Hidden(Box::new(Node::from(&**x))),
+            Meta::Item(item) => match ItemDoc::from_item(item) {
+                Some(item) => Node::Item(item),
+                None => Node::All(Vec::new()),
+            },
+        }
+    }
+}
+
+impl ItemDoc {
+    fn from_item(item: &Item) -> Option<Self> {
+        Some(match item.kind {
+            ItemKind::Flag => ItemDoc::Option(OptionDoc {
+                short: item.short,
+                long: item.long,
+                short_aliases: item.short_aliases.clone(),
+                long_aliases: item.hidden_aliases.clone(),
+                metavar: item.metavar,
+                help: item.help.clone(),
+                default: item.default.clone(),
+                section: item.section,
+            }),
+            ItemKind::Positional => ItemDoc::Positional(PositionalDoc {
+                metavar: item.positional_metavar(),
+                help: item.help.clone(),
+                default: item.default.clone(),
+                section: item.section,
+            }),
+            ItemKind::Command => match (item.long, &item.subparser) {
+                (Some(name), Some(sub)) => ItemDoc::Command(CommandDoc {
+                    name,
+                    aliases: item.aliases.clone(),
+                    hidden_aliases: item.hidden_aliases.clone(),
+                    help: item.help.clone(),
+                    section: item.section,
+                    parser: Box::new(ParserDoc::from(&**sub)),
+                }),
+                _ => ItemDoc::ExternalCommand {
+                    help: item.help.clone(),
+                },
+            },
+            ItemKind::Decor => return None,
+        })
+    }
+}
+
+impl ParserDoc {
+    fn new(info: &Info, parser_meta: &Meta, help_meta: &Meta) -> Self {
+        let own = |s: Option<&str>| s.map(String::from);
+        ParserDoc {
+            descr: own(info.descr),
+            header: own(info.header),
+            footer: own(info.footer),
+            usage: own(info.usage),
+            version: own(info.version),
+            sections: info.sections.iter().map(|s| String::from(*s)).collect(),
+            items: Node::from(parser_meta),
+            help_items: Node::from(help_meta),
+        }
+    }
+}
+
+impl From<&SubparserMeta> for ParserDoc {
+    fn from(sub: &SubparserMeta) -> Self {
+        ParserDoc::new(&sub.info, &sub.parser_meta, &sub.help_meta)
+    }
+}
+
+impl<T> Parser<T> {
+    /// Describe the structure of this parser, see [`introspect`](crate::introspect)
+    #[must_use]
+    pub fn describe(&self) -> Node {
+        Node::from(&self.meta)
+    }
+}
+
+impl<T> OptionParser<T> {
+    /// Describe the structure of this parser, see [`introspect`](crate::introspect)
+    #[must_use]
+    pub fn describe(&self) -> ParserDoc {
+        ParserDoc::new(&self.info, &self.parser_meta, &self.help_meta)
+    }
+}
+
+/// A change that can break existing invocations, see [`ParserDoc::breaking_changes`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Incompatibility {
+    /// Names of commands leading to the change, empty for top level parser
+    pub command: Vec<&'static str>,
+    /// What changed
+    pub kind: IncompatibilityKind,
+}
+
+/// Kind of [`Incompatibility`]
+///
+/// Options are named with dashes: `-v` or `--verbose`, positional items by their metavariable
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IncompatibilityKind {
+    /// Option name or command alias is no longer accepted
+    RemovedName(String),
+    /// Command is removed along with all its names
+    RemovedCommand(&'static str),
+    /// Positional item is removed
+    RemovedPositional(&'static str),
+    /// Optional item became required
+    BecameRequired(String),
+    /// Item that could be repeated can no longer be
+    NoLongerRepeatable(String),
+    /// Flag became an argument or an argument became a flag, new value is `true` for arguments
+    ArityChanged(String, bool),
+    /// New item is required
+    AddedRequired(String),
+}
+
+impl std::fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.command.is_empty() {
+            write!(f, "{}: ", self.command.join(" "))?;
+        }
+        match &self.kind {
+            IncompatibilityKind::RemovedName(name) => write!(f, "`{}` is no longer accepted", name),
+            IncompatibilityKind::RemovedCommand(name) => write!(f, "command `{}` is removed", name),
+            IncompatibilityKind::RemovedPositional(name) => {
+                write!(f, "positional item `{}` is removed", name)
+            }
+            IncompatibilityKind::BecameRequired(name) => write!(f, "`{}` became required", name),
+            IncompatibilityKind::NoLongerRepeatable(name) => {
+                write!(f, "`{}` can no longer be repeated", name)
+            }
+            IncompatibilityKind::ArityChanged(name, true) => {
+                write!(f, "`{}` now takes an argument", name)
+            }
+            IncompatibilityKind::ArityChanged(name, false) => {
+                write!(f, "`{}` no longer takes an argument", name)
+            }
+            IncompatibilityKind::AddedRequired(name) => {
+                write!(f, "`{}` is new and required", name)
+            }
+        }
+    }
+}
+
+fn option_names(opt: &OptionDoc) -> Vec<String> {
+    let shorts = opt.short.iter().chain(opt.short_aliases.iter());
+    let longs = opt.long.iter().chain(opt.long_aliases.iter());
+    longs
+        .map(|l| format!("--{}", l))
+        .chain(shorts.map(|s| format!("-{}", s)))
+        .collect()
+}
+
+fn commands<'a>(entries: &[Entry<'a>]) -> Vec<&'a CommandDoc> {
+    entries
+        .iter()
+        .filter_map(|entry| match entry.item {
+            ItemDoc::Command(cmd) => Some(cmd),
+            _ => None,
+        })
+        .collect()
+}
+
+fn command_names(cmd: &CommandDoc) -> impl Iterator<Item = &'static str> + '_ {
+    std::iter::once(cmd.name)
+        .chain(cmd.aliases.iter().copied())
+        .chain(cmd.hidden_aliases.iter().copied())
+}
+
+/// Report changes in occurrence of an item present in both versions
+fn compare_occurrence(old: Occurrence, new: Occurrence, name: &str) -> Vec<IncompatibilityKind> {
+    let mut res = Vec::new();
+    if old == Occurrence::Many && new != Occurrence::Many {
+        res.push(IncompatibilityKind::NoLongerRepeatable(name.to_owned()));
+    }
+    if old != Occurrence::Required && new == Occurrence::Required {
+        res.push(IncompatibilityKind::BecameRequired(name.to_owned()));
+    }
+    res
+}
+
+impl ParserDoc {
+    /// Changes in `new` that can break invocations accepted by `self`
+    ///
+    /// Looks for removed names of options and commands, removed positional items, items that
+    /// became required or can no longer be repeated and for flags that became arguments or
+    /// the other way around. Commands are compared recursively. Metavariables and help messages
+    /// can change freely.
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// let old = Info::default().for_parser(short('v').long("verbose").switch());
+    /// let new = Info::default().for_parser(long("verbose").switch());
+    /// let changes = old.describe().breaking_changes(&new.describe());
+    /// assert_eq!(changes[0].to_string(), "`-v` is no longer accepted");
+    /// ```
+    #[must_use]
+    pub fn breaking_changes(&self, new: &ParserDoc) -> Vec<Incompatibility> {
+        let mut res = Vec::new();
+        compare_parsers(self, new, &mut Vec::new(), &mut res);
+        res
+    }
+}
+
+fn compare_parsers(
+    old: &ParserDoc,
+    new: &ParserDoc,
+    path: &mut Vec<&'static str>,
+    res: &mut Vec<Incompatibility>,
+) {
+    let mut push = |kind| {
+        res.push(Incompatibility {
+            command: path.clone(),
+            kind,
+        });
+    };
+    let old_entries = old.items.entries();
+    let new_entries = new.items.entries();
+
+    // options, matched by any of their names
+    let options = |entries: &[Entry<'_>]| {
+        let mut res = Vec::new();
+        for entry in entries {
+            if let ItemDoc::Option(opt) = entry.item {
+                res.push((option_names(opt), opt.metavar.is_some(), entry.occurrence));
+            }
+        }
+        res
+    };
+    let old_options = options(&old_entries);
+    let new_options = options(&new_entries);
+    let find = |name: &String| new_options.iter().find(|(names, ..)| names.contains(name));
+    for (names, takes_arg, occurrence) in &old_options {
+        let mut reported = false;
+        for name in names {
+            match find(name) {
+                None => push(IncompatibilityKind::RemovedName(name.clone())),
+                Some((_, new_takes_arg, new_occurrence)) if !reported => {
+                    reported = true;
+                    if takes_arg != new_takes_arg {
+                        push(IncompatibilityKind::ArityChanged(
+                            name.clone(),
+                            *new_takes_arg,
+                        ));
+                    }
+                    for kind in compare_occurrence(*occurrence, *new_occurrence, name) {
+                        push(kind);
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    for (names, _, occurrence) in &new_options {
+        let existed = old_options
+            .iter()
+            .any(|(old_names, ..)| old_names.iter().any(|n| names.contains(n)));
+        if !existed && *occurrence == Occurrence::Required {
+            push(IncompatibilityKind::AddedRequired(names[0].clone()));
+        }
+    }
+
+    // positional items, matched by their position
+    let positionals = |entries: &[Entry<'_>]| {
+        let mut res = Vec::new();
+        for entry in entries {
+            if let ItemDoc::Positional(pos) = entry.item {
+                res.push((pos.metavar, entry.occurrence));
+            }
+        }
+        res
+    };
+    let old_pos = positionals(&old_entries);
+    let new_pos = positionals(&new_entries);
+    for (ix, (metavar, occurrence)) in old_pos.iter().enumerate() {
+        match new_pos.get(ix) {
+            None => push(IncompatibilityKind::RemovedPositional(metavar)),
+            Some((new_metavar, new_occurrence)) => {
+                for kind in compare_occurrence(*occurrence, *new_occurrence, new_metavar) {
+                    push(kind);
+                }
+            }
+        }
+    }
+    for (metavar, occurrence) in new_pos.iter().skip(old_pos.len()) {
+        if *occurrence == Occurrence::Required {
+            push(IncompatibilityKind::AddedRequired((*metavar).to_owned()));
+        }
+    }
+
+    // commands, matched by any of their names
+    let new_commands = commands(&new_entries);
+    let mut nested = Vec::new();
+    for cmd in commands(&old_entries) {
+        let found = new_commands
+            .iter()
+            .find(|new| command_names(cmd).any(|n| command_names(new).any(|m| m == n)));
+        match found {
+            None => push(IncompatibilityKind::RemovedCommand(cmd.name)),
+            Some(new_cmd) => {
+                for name in command_names(cmd) {
+                    if !command_names(new_cmd).any(|n| n == name) {
+                        push(IncompatibilityKind::RemovedName(name.to_owned()));
+                    }
+                }
+                nested.push((cmd, *new_cmd));
+            }
+        }
+    }
+    for (cmd, new_cmd) in nested {
+        path.push(cmd.name);
+        compare_parsers(&cmd.parser, &new_cmd.parser, path, res);
+        path.pop();
+    }
+}
@@ -0,0 +1,519 @@
+//! Machine readable description of a parser, enabled by `json` feature
+//!
+//! Output is built from [`introspect`](crate::introspect) types and is pretty printed with stable
+//! field order so descriptions of different versions of an application can be diffed.
+use crate::introspect::{CommandDoc, ItemDoc, Node, OptionDoc, ParserDoc, PositionalDoc};
+use crate::OptionParser;
+
+enum Json {
+    Null,
+    Str(String),
+    Arr(Vec<Json>),
+    Obj(Vec<(String, Json)>),
+}
+
+fn obj(fields: Vec<(&str, Json)>) -> Json {
+    Json::Obj(
+        fields
+            .into_iter()
+            .map(|(key, val)| (key.to_owned(), val))
+            .collect(),
+    )
+}
+
+impl From<Option<&str>> for Json {
+    fn from(s: Option<&str>) -> Self {
+        match s {
+            Some(s) => Json::Str(s.to_owned()),
+            None => Json::Null,
+        }
+    }
+}
+
+impl From<&Node> for Json {
+    fn from(node: &Node) -> Self {
+        let go = |xs: &[Node]| Json::Arr(xs.iter().map(Json::from).collect());
+        match node {
+            Node::All(xs) => obj(vec![("all", go(xs))]),
+            Node::Any(xs) => obj(vec![("any", go(xs))]),
+            Node::Optional(x) => obj(vec![("optional", Json::from(&**x))]),
+            Node::Many(x) => obj(vec![("many", Json::from(&**x))]),
+            Node::Group(msg, x) => obj(vec![(
+                "group",
+                obj(vec![
+                    ("help", Json::Str(msg.clone())),
+                    ("item", Json::from(&**x)),
+                ]),
+            )]),
+            Node::Hidden(x) => obj(vec![("hidden", Json::from(&**x))]),
+            Node::Item(item) => Json::from(item),
+        }
+    }
+}
+
+impl From<&ItemDoc> for Json {
+    fn from(item: &ItemDoc) -> Self {
+        let strs = |xs: &[&str]| Json::Arr(xs.iter().map(|x| Json::from(Some(*x))).collect());
+        let kind = |k: &str| ("kind", Json::Str(k.to_owned()));
+        match item {
+            ItemDoc::Option(opt) => {
+                let short = opt.short.map(String::from);
+                let short_aliases = opt
+                    .short_aliases
+                    .iter()
+                    .map(|c| Json::Str(c.to_string()))
+                    .collect();
+                obj(vec![
+                    kind("option"),
+                    ("short", Json::from(short.as_deref())),
+                    ("long", Json::from(opt.long)),
+                    ("short_aliases", Json::Arr(short_aliases)),
+                    ("long_aliases", strs(&opt.long_aliases)),
+                    ("metavar", Json::from(opt.metavar)),
+                    ("help", Json::from(opt.help.as_deref())),
+                    ("default", Json::from(opt.default.as_deref())),
+                    ("section", Json::from(opt.section)),
+                ])
+            }
+            ItemDoc::Positional(pos) => obj(vec![
+                kind("positional"),
+                ("metavar", Json::from(Some(pos.metavar))),
+                ("help", Json::from(pos.help.as_deref())),
+                ("default", Json::from(pos.default.as_deref())),
+                ("section", Json::from(pos.section)),
+            ]),
+            ItemDoc::Command(cmd) => obj(vec![
+                kind("command"),
+                ("name", Json::from(Some(cmd.name))),
+                ("aliases", strs(&cmd.aliases)),
+                ("hidden_aliases", strs(&cmd.hidden_aliases)),
+                ("help", Json::from(cmd.help.as_deref())),
+                ("section", Json::from(cmd.section)),
+                ("parser", Json::from(&*cmd.parser)),
+            ]),
+            ItemDoc::ExternalCommand { help } => obj(vec![
+                kind("external_command"),
+                ("help", Json::from(help.as_deref())),
+            ]),
+        }
+    }
+}
+
+impl From<&ParserDoc> for Json {
+    fn from(doc: &ParserDoc) -> Self {
+        obj(vec![
+            ("descr", Json::from(doc.descr.as_deref())),
+            ("header", Json::from(doc.header.as_deref())),
+            ("footer", Json::from(doc.footer.as_deref())),
+            ("usage", Json::from(doc.usage.as_deref())),
+            ("version", Json::from(doc.version.as_deref())),
+            (
+                "sections",
+                Json::Arr(doc.sections.iter().map(|s| Json::Str(s.clone())).collect()),
+            ),
+            ("items", Json::from(&doc.items)),
+            ("help_items", Json::from(&doc.help_items)),
+        ])
+    }
+}
+
+fn write_str(res: &mut String, s: &str) {
+    res.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+}
+
+fn write_indent(res: &mut String, depth: usize) {
+    res.push('\n');
+    for _ in 0..depth {
+        res.push_str("  ");
+    }
+}
+
+impl Json {
+    fn write(&self, res: &mut String, depth: usize) {
+        match self {
+            Json::Null => res.push_str("null"),
+            Json::Str(s) => write_str(res, s),
+            Json::Arr(xs) if xs.is_empty() => res.push_str("[]"),
+            Json::Arr(xs) => {
+                res.push('[');
+                for (ix, x) in xs.iter().enumerate() {
+                    if ix > 0 {
+                        res.push(',');
+                    }
+                    write_indent(res, depth + 1);
+                    x.write(res, depth + 1);
+                }
+                write_indent(res, depth);
+                res.push(']');
+            }
+            Json::Obj(xs) => {
+                res.push('{');
+                for (ix, (key, val)) in xs.iter().enumerate() {
+                    if ix > 0 {
+                        res.push(',');
+                    }
+                    write_indent(res, depth + 1);
+                    write_str(res, key);
+                    res.push_str(": ");
+                    val.write(res, depth + 1);
+                }
+                write_indent(res, depth);
+                res.push('}');
+            }
+        }
+    }
+}
+
+/// Reads JSON produced by `Json::write`, numbers and booleans are not supported
+struct Reader<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn error<T>(&self, msg: &str) -> Result<T, String> {
+        Err(format!("{} at offset {}", msg, self.pos))
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.input[self.pos..].chars().next()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            Ok(())
+        } else {
+            self.error(&format!("Expected `{}`", c))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        match self.peek() {
+            Some('"') => Ok(Json::Str(self.string()?)),
+            Some('[') => {
+                self.pos += 1;
+                let mut res = Vec::new();
+                if self.peek() == Some(']') {
+                    self.pos += 1;
+                    return Ok(Json::Arr(res));
+                }
+                loop {
+                    res.push(self.value()?);
+                    if self.peek() == Some(']') {
+                        self.pos += 1;
+                        return Ok(Json::Arr(res));
+                    }
+                    self.expect(',')?;
+                }
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut res = Vec::new();
+                if self.peek() == Some('}') {
+                    self.pos += 1;
+                    return Ok(Json::Obj(res));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    res.push((key, self.value()?));
+                    if self.peek() == Some('}') {
+                        self.pos += 1;
+                        return Ok(Json::Obj(res));
+                    }
+                    self.expect(',')?;
+                }
+            }
+            Some('n') if self.input[self.pos..].starts_with("null") => {
+                self.pos += 4;
+                Ok(Json::Null)
+            }
+            Some(_) => self.error("Unexpected input"),
+            None => self.error("Unexpected end of input"),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut res = String::new();
+        let mut chars = self.input[self.pos..].char_indices();
+        while let Some((ix, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += ix + 1;
+                    return Ok(res);
+                }
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('"') => res.push('"'),
+                    Some('\\') => res.push('\\'),
+                    Some('/') => res.push('/'),
+                    Some('n') => res.push('\n'),
+                    Some('r') => res.push('\r'),
+                    Some('t') => res.push('\t'),
+                    Some('u') => {
+                        let code = chars.by_ref().take(4).map(|(_, c)| c).collect::<String>();
+                        match u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                            Some(c) => res.push(c),
+                            None => {
+                                self.pos += ix;
+                                return self.error("Invalid unicode escape");
+                            }
+                        }
+                    }
+                    _ => {
+                        self.pos += ix;
+                        return self.error("Invalid escape");
+                    }
+                },
+                c => res.push(c),
+            }
+        }
+        self.pos = self.input.len();
+        self.error("Unterminated string")
+    }
+}
+
+impl Json {
+    fn field(&self, name: &str) -> Result<&Json, String> {
+        match self {
+            Json::Obj(fields) => match fields.iter().find(|(key, _)| key == name) {
+                Some((_, val)) => Ok(val),
+                None => Err(format!("Missing field `{}`", name)),
+            },
+            _ => Err(format!("Expected an object with field `{}`", name)),
+        }
+    }
+
+    fn str(&self) -> Result<&str, String> {
+        match self {
+            Json::Str(s) => Ok(s),
+            _ => Err("Expected a string".to_owned()),
+        }
+    }
+
+    fn opt_str(&self) -> Result<Option<&str>, String> {
+        match self {
+            Json::Null => Ok(None),
+            val => val.str().map(Some),
+        }
+    }
+
+    fn arr(&self) -> Result<&[Json], String> {
+        match self {
+            Json::Arr(xs) => Ok(xs),
+            _ => Err("Expected an array".to_owned()),
+        }
+    }
+
+    fn string(&self, name: &str) -> Result<Option<String>, String> {
+        Ok(self.field(name)?.opt_str()?.map(String::from))
+    }
+
+    fn name(&self, name: &str) -> Result<Option<&'static str>, String> {
+        Ok(self
+            .field(name)?
+            .opt_str()?
+            .map(|s| crate::intern(s.to_owned())))
+    }
+
+    fn names(&self, name: &str) -> Result<Vec<&'static str>, String> {
+        self.field(name)?
+            .arr()?
+            .iter()
+            .map(|x| Ok(crate::intern(x.str()?.to_owned())))
+            .collect()
+    }
+
+    fn chars(&self, name: &str) -> Result<Vec<char>, String> {
+        self.field(name)?.arr()?.iter().map(to_char).collect()
+    }
+}
+
+fn to_char(val: &Json) -> Result<char, String> {
+    let s = val.str()?;
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(format!("Expected a single character, got {:?}", s)),
+    }
+}
+
+impl TryFrom<&Json> for Node {
+    type Error = String;
+
+    fn try_from(val: &Json) -> Result<Self, Self::Error> {
+        let go = |xs: &Json| -> Result<Vec<Node>, String> {
+            xs.arr()?.iter().map(Node::try_from).collect()
+        };
+        let inner = |x: &Json| Node::try_from(x).map(Box::new);
+        Ok(match val {
+            Json::Obj(fields) if fields.len() == 1 => {
+                let (key, x) = &fields[0];
+                match key.as_str() {
+                    "all" => Node::All(go(x)?),
+                    "any" => Node::Any(go(x)?),
+                    "optional" => Node::Optional(inner(x)?),
+                    "many" => Node::Many(inner(x)?),
+                    "group" => {
+                        Node::Group(x.field("help")?.str()?.to_owned(), inner(x.field("item")?)?)
+                    }
+                    "hidden" => Node::Hidden(inner(x)?),
+                    _ => return Err(format!("Unknown node `{}`", key)),
+                }
+            }
+            _ => Node::Item(ItemDoc::try_from(val)?),
+        })
+    }
+}
+
+impl TryFrom<&Json> for ItemDoc {
+    type Error = String;
+
+    fn try_from(val: &Json) -> Result<Self, Self::Error> {
+        Ok(match val.field("kind")?.str()? {
+            "option" => ItemDoc::Option(OptionDoc {
+                short: match val.field("short")? {
+                    Json::Null => None,
+                    short => Some(to_char(short)?),
+                },
+                long: val.name("long")?,
+                short_aliases: val.chars("short_aliases")?,
+                long_aliases: val.names("long_aliases")?,
+                metavar: val.name("metavar")?,
+                help: val.string("help")?,
+                default: val.string("default")?,
+                section: val.name("section")?,
+            }),
+            "positional" => ItemDoc::Positional(PositionalDoc {
+                metavar: crate::intern(val.field("metavar")?.str()?.to_owned()),
+                help: val.string("help")?,
+                default: val.string("default")?,
+                section: val.name("section")?,
+            }),
+            "command" => ItemDoc::Command(CommandDoc {
+                name: crate::intern(val.field("name")?.str()?.to_owned()),
+                aliases: val.names("aliases")?,
+                hidden_aliases: val.names("hidden_aliases")?,
+                help: val.string("help")?,
+                section: val.name("section")?,
+                parser: Box::new(ParserDoc::try_from(val.field("parser")?)?),
+            }),
+            "external_command" => ItemDoc::ExternalCommand {
+                help: val.string("help")?,
+            },
+            kind => return Err(format!("Unknown item kind `{}`", kind)),
+        })
+    }
+}
+
+impl TryFrom<&Json> for ParserDoc {
+    type Error = String;
+
+    fn try_from(val: &Json) -> Result<Self, Self::Error> {
+        let sections = val.field("sections")?.arr()?;
+        Ok(ParserDoc {
+            descr: val.string("descr")?,
+            header: val.string("header")?,
+            footer: val.string("footer")?,
+            usage: val.string("usage")?,
+            version: val.string("version")?,
+            sections: sections
+                .iter()
+                .map(|s| s.str().map(String::from))
+                .collect::<Result<_, _>>()?,
+            items: Node::try_from(val.field("items")?)?,
+            help_items: Node::try_from(val.field("help_items")?)?,
+        })
+    }
+}
+
+impl ParserDoc {
+    /// Render this description as JSON
+    ///
+    /// See [`OptionParser::to_json`] for details
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let mut res = String::new();
+        Json::from(self).write(&mut res, 0);
+        res.push('\n');
+        res
+    }
+
+    /// Load a description saved with [`to_json`](ParserDoc::to_json), requires `json` feature
+    ///
+    /// Use it to compare a description saved by an earlier release of the application with the
+    /// current one using [`breaking_changes`](ParserDoc::breaking_changes). Names and
+    /// metavariables are kept in memory for the rest of the program.
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// use bpaf::introspect::ParserDoc;
+    /// let old = Info::default().for_parser(short('v').long("verbose").switch());
+    /// let saved = old.to_json();
+    ///
+    /// let new = Info::default().for_parser(long("verbose").switch());
+    /// let changes = ParserDoc::from_json(&saved)?.breaking_changes(&new.describe());
+    /// assert_eq!(changes[0].to_string(), "`-v` is no longer accepted");
+    /// # Ok::<(), String>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a message describing the problem if `json` is not a valid description
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let mut reader = Reader {
+            input: json,
+            pos: 0,
+        };
+        let val = reader.value()?;
+        if reader.peek().is_some() {
+            return reader.error("Unexpected input after the description");
+        }
+        ParserDoc::try_from(&val)
+    }
+}
+
+impl<T> OptionParser<T> {
+    /// Describe the parser as JSON, requires `json` feature
+    ///
+    /// Result is an object with `descr`, `header`, `footer`, `usage` and `version` fields taken
+    /// from [`Info`](crate::Info), `items` with the parser structure and `help_items` with
+    /// `--help` and `--version`. Structure is made of nested objects with a single field: `all`,
+    /// `any`, `optional`, `many`, `hidden` or `group`, or of items with a `kind` field: `option`,
+    /// `positional`, `command` or `external_command`. Commands contain their inner parsers in the
+    /// `parser` field. Missing values are `null`.
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// let parser = Info::default()
+    ///     .version("1.0")
+    ///     .for_parser(long("name").argument("NAME"));
+    /// let json = parser.to_json();
+    /// assert!(json.contains(r#""version": "1.0""#));
+    /// assert!(json.contains(r#""metavar": "NAME""#));
+    /// ```
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        self.describe().to_json()
+    }
+}
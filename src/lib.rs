@@ -2,25 +2,47 @@
 #![warn(rustdoc::missing_doc_code_examples)]
 #![doc = include_str!("../README.md")]
 
-use std::rc::Rc;
 use std::str::FromStr;
 
+/// Items used by macros, not a part of the public API
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(not(feature = "sync"))]
+    pub use std::rc::Rc;
+    #[cfg(feature = "sync")]
+    pub use std::sync::Arc as Rc;
+}
+use crate::__private::Rc;
+
 pub mod params;
 
+pub mod introspect;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "testing")]
+pub mod testing;
+
 mod args;
+mod docs;
 #[doc(hidden)]
 pub mod info;
+mod unparse;
 
-use crate::{args::Word, info::Error, info::Item};
+use crate::{
+    args::Word,
+    info::{Error, Item, ItemKind},
+};
 
 #[cfg(test)]
 mod tests;
 #[doc(inline)]
 pub use crate::args::Args;
 #[doc(inline)]
-pub use crate::info::{Info, Meta, OptionParser};
+pub use crate::info::{Info, Meta, OptionParser, UsageStyle};
 #[doc(inline)]
 pub use crate::params::*;
+#[doc(inline)]
+pub use crate::unparse::{Unparse, UnparseArgs};
 
 #[cfg(feature = "bpaf_derive")]
 pub use bpaf_derive::Bpaf;
@@ -112,7 +134,7 @@ macro_rules! construct {
     (@prepare [alt] [$first:ident $($fields:ident)*]) => { $first $(.or_else($fields))*  };
     (@prepare $ty:tt [$($fields:tt)*]) => {{
         $crate::Parser {
-            parse: ::std::rc::Rc::new(move |args| {
+            parse: $crate::__private::Rc::new(move |args| {
                 $(let ($fields , args) = ($fields . parse)(args)?;)*
                 Ok((construct!(@make $ty [$($fields)*]), args))
             }),
@@ -129,8 +151,34 @@ macro_rules! construct {
 
 #[doc(hidden)]
 /// A bit more user friendly alias for parsing function
+#[cfg(not(feature = "sync"))]
 pub type DynParse<T> = dyn Fn(Args) -> Result<(T, Args), Error>;
 
+#[doc(hidden)]
+/// A bit more user friendly alias for parsing function
+#[cfg(feature = "sync")]
+pub type DynParse<T> = dyn Fn(Args) -> Result<(T, Args), Error> + Send + Sync;
+
+/// `Send + Sync` with `sync` feature enabled, implemented for all types otherwise
+///
+/// Functions and values captured by parsers must implement this trait so that with `sync`
+/// feature parsers can be shared between threads. Since enabling `sync` adds these bounds to
+/// all the user code, the feature is not additive and should be enabled by applications only.
+#[cfg(feature = "sync")]
+pub trait MaybeSendSync: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T: Send + Sync> MaybeSendSync for T {}
+
+/// `Send + Sync` with `sync` feature enabled, implemented for all types otherwise
+///
+/// Functions and values captured by parsers must implement this trait so that with `sync`
+/// feature parsers can be shared between threads. Since enabling `sync` adds these bounds to
+/// all the user code, the feature is not additive and should be enabled by applications only.
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSendSync {}
+#[cfg(not(feature = "sync"))]
+impl<T> MaybeSendSync for T {}
+
 /// Simple or composed argument parser
 #[derive(Clone)]
 pub struct Parser<T> {
@@ -158,7 +206,7 @@ impl<T> Parser<T> {
     #[must_use]
     pub fn pure(val: T) -> Parser<T>
     where
-        T: 'static + Clone,
+        T: 'static + Clone + MaybeSendSync,
     {
         let parse = move |i| Ok((val.clone(), i));
         Parser {
@@ -261,7 +309,7 @@ impl<T> Parser<T> {
     pub fn fail<M>(msg: M) -> Parser<T>
     where
         String: From<M>,
-        M: Clone + 'static,
+        M: Clone + MaybeSendSync + 'static,
     {
         Parser {
             meta: Meta::Empty,
@@ -280,24 +328,28 @@ impl<T> Parser<T> {
     /// # drop(n);
     /// ```
     ///
-    /// # Panics
-    /// Panics if parser succeeds without consuming any input: any parser modified with
-    /// `many` must consume something,
+    /// Any parser modified with `many` must consume something: if it succeeds without consuming
+    /// any input, such as [`optional`](Parser::optional) or [`switch`](params::Named::switch)
+    /// parsers, `many` fails with an error. Use [`Info::try_for_parser`] to detect such
+    /// parsers before running them.
     #[must_use]
     pub fn many(self) -> Parser<Vec<T>>
     where
         T: 'static,
     {
+        let meta = self.meta.clone();
         let parse = move |mut i: Args| {
             let mut res = Vec::new();
             let mut size = i.len();
             while let Ok((elt, new_i)) = (self.parse)(i.clone()) {
                 let new_size = new_i.len();
-                #[allow(clippy::panic)]
                 if new_size < size {
                     size = new_size;
                 } else {
-                    panic!("many can't be used with non failing parser")
+                    return Err(Error::Stderr(format!(
+                        "many can't be used with a parser that succeeds without consuming anything: {}",
+                        meta
+                    )));
                 }
                 i = new_i;
                 res.push(elt);
@@ -322,7 +374,7 @@ impl<T> Parser<T> {
     #[must_use]
     pub fn guard<F>(self, m: F, message: &'static str) -> Parser<T>
     where
-        F: Fn(&T) -> bool + 'static,
+        F: Fn(&T) -> bool + MaybeSendSync + 'static,
         T: 'static,
     {
         let parse = move |i: Args| match (self.parse)(i) {
@@ -369,7 +421,7 @@ impl<T> Parser<T> {
     /// ```
     pub fn optional(self) -> Parser<Option<T>>
     where
-        T: 'static + Clone,
+        T: 'static + Clone + MaybeSendSync,
     {
         self.map(Some).fallback(None)
     }
@@ -385,7 +437,7 @@ impl<T> Parser<T> {
     /// ```
     pub fn map<F, B>(self, map: F) -> Parser<B>
     where
-        F: Fn(T) -> B + 'static,
+        F: Fn(T) -> B + MaybeSendSync + 'static,
         T: 'static,
     {
         let parse = move |args: Args| {
@@ -411,7 +463,7 @@ impl<T> Parser<T> {
     /// ```
     pub fn parse<F, B, E>(self, map: F) -> Parser<B>
     where
-        F: Fn(T) -> Result<B, E> + 'static,
+        F: Fn(T) -> Result<B, E> + MaybeSendSync + 'static,
         T: 'static,
         E: ToString,
     {
@@ -420,8 +472,8 @@ impl<T> Parser<T> {
 
             match map(t) {
                 Ok(ok) => Ok((ok, args)),
-                Err(e) => Err(Error::Stderr(
-                    if let Some(Word { utf8: Some(w), .. }) = args.current {
+                Err(e) => Err(args.error_at_current(
+                    if let Some(Word { utf8: Some(w), .. }) = &args.current {
                         format!("Couldn't parse {:?}: {}", w, e.to_string())
                     } else {
                         format!("Couldn't parse: {}", e.to_string())
@@ -446,11 +498,11 @@ impl<T> Parser<T> {
     #[must_use]
     pub fn fallback(self, val: T) -> Parser<T>
     where
-        T: Clone + 'static,
+        T: Clone + MaybeSendSync + 'static,
     {
         let parse = move |i: Args| match (self.parse)(i.clone()) {
             Ok(ok) => Ok(ok),
-            e @ Err(Error::Stderr(_)) => e,
+            e @ Err(Error::Stderr(_) | Error::Context(_)) => e,
             Err(_) => Ok((val.clone(), i)),
         };
         Parser {
@@ -459,6 +511,35 @@ impl<T> Parser<T> {
         }
     }
 
+    /// Use this value as default if value is not present on a command line and show it in the help
+    ///
+    /// Same as [`fallback`](Parser::fallback), but help message for the items of this parser
+    /// will contain `[default: 42]`
+    /// ```rust
+    /// # use bpaf::*;
+    /// let n = short('n').argument("NUM").from_str::<u32>().fallback_display(42);
+    /// # drop(n)
+    /// ```
+    #[must_use]
+    pub fn fallback_display(self, val: T) -> Parser<T>
+    where
+        T: Clone + std::fmt::Display + MaybeSendSync + 'static,
+    {
+        let default = val.to_string();
+        let meta = self.meta.map_items(&|item| match item.kind {
+            ItemKind::Flag | ItemKind::Positional => Item {
+                default: Some(default.clone()),
+                ..item
+            },
+            ItemKind::Command | ItemKind::Decor => item,
+        });
+        Parser {
+            parse: self.parse,
+            meta,
+        }
+        .fallback(val)
+    }
+
     /// Use value produced by this function as default if value is not present
     ///
     /// Would still fail if value is present but failure comes from some transformation
@@ -471,13 +552,13 @@ impl<T> Parser<T> {
     #[must_use]
     pub fn fallback_with<F, E>(self, val: F) -> Parser<T>
     where
-        F: Fn() -> Result<T, E> + Clone + 'static,
+        F: Fn() -> Result<T, E> + Clone + MaybeSendSync + 'static,
         E: ToString,
         T: Clone + 'static,
     {
         let parse = move |i: Args| match (self.parse)(i.clone()) {
             Ok(ok) => Ok(ok),
-            e @ Err(Error::Stderr(_)) => e,
+            e @ Err(Error::Stderr(_) | Error::Context(_)) => e,
             Err(_) => match val() {
                 Ok(ok) => Ok((ok, i)),
                 Err(e) => Err(Error::Stderr(e.to_string())),
@@ -499,7 +580,7 @@ impl<T> Parser<T> {
     #[must_use]
     pub fn default(self) -> Parser<T>
     where
-        T: Default + 'static + Clone,
+        T: Default + 'static + Clone + MaybeSendSync,
     {
         self.fallback(T::default())
     }
@@ -522,6 +603,33 @@ impl<T> Parser<T> {
         }
     }
 
+    /// Show items of this parser in a separate help section
+    ///
+    /// Options, positional items and commands of the section are listed under `name` in the
+    /// help and in generated documentation instead of the usual "Available options" and
+    /// similar headings, usage line stays the same. Items already placed into a section keep
+    /// it. See [`Info::sections`] to change the order of sections.
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// let host = long("host").help("Server to connect to").argument("HOST");
+    /// let port = long("port").help("Server port").argument("PORT");
+    /// let net = construct!(host, port).help_section("Network options");
+    /// let parser = Info::default().for_parser(net);
+    /// let help = parser.render_help().unwrap();
+    /// assert!(help.contains("\nNetwork options:\n        --host <HOST>  Server to connect to\n"));
+    /// ```
+    #[must_use]
+    pub fn help_section(self, name: &'static str) -> Parser<T> {
+        Self {
+            parse: self.parse,
+            meta: self.meta.map_items(&|item| Item {
+                section: item.section.or(Some(name)),
+                ..item
+            }),
+        }
+    }
+
     /// Ignore this parser during any sort of help generation
     ///
     /// Best used for optional parsers or parsers with a defined fallback
@@ -545,7 +653,83 @@ impl<T> Parser<T> {
             parse: Rc::new(move |args: Args| {
                 (self.parse)(args).map_err(|_| Error::Missing(Vec::new()))
             }),
-            meta: Meta::Id,
+            meta: Meta::Hidden(Box::new(self.meta)),
+        }
+    }
+
+    /// Add a prefix to long names of all the flags and arguments in this parser
+    ///
+    /// Parser will accept `--db-host` in place of `--host`, short names, positional items and
+    /// commands are left as is. Useful to include the same parser several times.
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// fn connection() -> Parser<(String, u16)> {
+    ///     let host = long("host").argument("HOST");
+    ///     let port = long("port").argument("PORT").from_str::<u16>();
+    ///     construct!(host, port)
+    /// }
+    /// // accepts `--db-host` and `--db-port`
+    /// let db = connection().long_prefix("db");
+    /// // accepts `--cache-host` and `--cache-port`
+    /// let cache = connection().long_prefix("cache");
+    /// let both = construct!(db, cache);
+    /// # drop(both);
+    /// ```
+    #[must_use]
+    pub fn long_prefix(self, prefix: &'static str) -> Parser<T>
+    where
+        T: 'static,
+    {
+        let names = self
+            .meta
+            .flags()
+            .into_iter()
+            .flat_map(|i| i.long.into_iter().chain(i.hidden_aliases))
+            .map(|l| (l, intern(format!("{}-{}", prefix, l))))
+            .collect::<Vec<_>>();
+        let rename = move |mut item: Item| {
+            if item.kind == ItemKind::Flag {
+                let rename = |l| names.iter().find(|n| n.0 == l).map_or(l, |n| n.1);
+                item.long = item.long.map(rename);
+                item.hidden_aliases = item.hidden_aliases.into_iter().map(rename).collect();
+            }
+            item
+        };
+        let meta = self.meta.clone().map_items(&rename);
+        let parse = move |args: Args| {
+            args.with_long_prefix(prefix, |args| (self.parse)(args))
+                .map_err(|err| match err {
+                    Error::Missing(metas) => {
+                        Error::Missing(metas.into_iter().map(|m| m.map_items(&rename)).collect())
+                    }
+                    err @ (Error::Stdout(_) | Error::Stderr(_) | Error::Context(_)) => err,
+                })
+        };
+        Parser {
+            parse: Rc::new(parse),
+            meta,
+        }
+    }
+}
+
+/// Static copy of a name created at runtime, such as a long name with a prefix
+///
+/// Every distinct name is allocated once and kept for the rest of the program so parsers can be
+/// created repeatedly without using more memory.
+fn intern(name: String) -> &'static str {
+    use std::collections::BTreeSet;
+    use std::sync::Mutex;
+    static NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+    let mut names = NAMES
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    match names.get(name.as_str()) {
+        Some(name) => name,
+        None => {
+            let name = Box::leak(name.into_boxed_str());
+            names.insert(name);
+            name
         }
     }
 }
@@ -568,6 +752,57 @@ impl Parser<String> {
     {
         self.parse(|s| T::from_str(&s))
     }
+
+    /// Accept only values from a fixed set
+    ///
+    /// Names of the values and their help messages are listed in the help for this argument.
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// let values = Values::new()
+    ///     .value("fast", 1)
+    ///     .help("Optimize for speed")
+    ///     .value("small", 2)
+    ///     .help("Optimize for size");
+    /// let opt = long("opt").argument("OPT").values(values);
+    /// // accepts "--opt fast" and "--opt small" but rejects "--opt medium"
+    /// # drop(opt)
+    /// ```
+    #[must_use]
+    pub fn values<T>(self, values: Values<T>) -> Parser<T>
+    where
+        T: Clone + MaybeSendSync + 'static,
+    {
+        let lines = values.help_lines();
+        let meta = self.meta.clone().map_items(&|mut item| {
+            if item.kind != ItemKind::Command {
+                item.help = Some(match item.help {
+                    Some(help) => format!("{}\n{}", help, lines),
+                    None => lines.clone(),
+                });
+            }
+            item
+        });
+        let parser = self.parse(move |s| {
+            values
+                .find(&s)
+                .cloned()
+                .ok_or_else(|| format!("expected one of {}", values.names()))
+        });
+        Parser {
+            parse: parser.parse,
+            meta,
+        }
+    }
+}
+
+/// Outcome of [`OptionParser::run_with`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RunOutcome<T> {
+    /// Parsing succeeded with this value
+    Parsed(T),
+    /// A message was printed, program should exit with this code
+    Exit(i32),
 }
 
 /// Unsuccessful command line parsing outcome
@@ -632,45 +867,127 @@ impl<T> OptionParser<T> {
     /// ```
     #[must_use]
     pub fn run(self) -> T {
-        let mut pos_only = false;
-        let mut vec = Vec::new();
-        for arg in std::env::args_os().skip(1) {
-            args::push_vec(&mut vec, arg, &mut pos_only);
+        self.run_with_args(Args::from_os_strings(std::env::args_os().skip(1)))
+    }
+
+    fn run_with_args(self, args: Args) -> T {
+        let res = self.parse_args(args);
+        let stdout = &mut std::io::stdout();
+        let stderr = &mut std::io::stderr();
+        match self.report(res, stdout, stderr) {
+            RunOutcome::Parsed(t) => t,
+            RunOutcome::Exit(code) => std::process::exit(code),
         }
+    }
 
-        match self.run_inner(Args::from(vec)) {
-            Ok(t) => t,
-            Err(ParseFailure::Stdout(msg)) => {
-                println!("{}", msg);
-                std::process::exit(0);
-            }
-            Err(ParseFailure::Stderr(msg)) => {
-                eprintln!("{}", msg);
-                std::process::exit(1);
+    /// Execute the [`OptionParser`] with custom arguments and outputs, leave exiting to the caller
+    ///
+    /// `args` are the arguments without the program name. Help and version information is
+    /// written to `stdout`, error messages to `stderr`, in both cases result contains an exit
+    /// code the program is expected to exit with: `0` for help and version, `1` for errors
+    /// unless changed with [`Info::error_code`]. Errors writing the messages are ignored.
+    ///
+    /// Same as with [`run`](OptionParser::run), error messages are followed by the command
+    /// line with the failing argument marked if there is one, usage line of the command the
+    /// error happened in and a hint about the help flag.
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// let verbose = short('v').switch();
+    /// let parser = Info::default().error_code(2).for_parser(verbose);
+    ///
+    /// let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+    /// let r = parser.run_with(["-v"], &mut stdout, &mut stderr);
+    /// assert_eq!(r, RunOutcome::Parsed(true));
+    ///
+    /// let r = parser.run_with(["-v", "-x"], &mut stdout, &mut stderr);
+    /// assert_eq!(r, RunOutcome::Exit(2));
+    /// let expected = "\
+    /// -x is not expected in this context
+    ///     -v -x
+    ///        ^^
+    /// Usage: [-v]
+    /// Pass --help for usage information
+    /// ";
+    /// assert_eq!(String::from_utf8(stderr).unwrap(), expected);
+    /// ```
+    pub fn run_with<I, O, E>(&self, args: I, stdout: &mut O, stderr: &mut E) -> RunOutcome<T>
+    where
+        I: IntoIterator,
+        I::Item: Into<std::ffi::OsString>,
+        O: std::io::Write,
+        E: std::io::Write,
+    {
+        let args = Args::from_os_strings(args.into_iter().map(Into::into));
+        self.report(self.parse_args(args), stdout, stderr)
+    }
+
+    /// Print messages from a parsing failure and pick the exit code
+    ///
+    /// Parsing errors are printed with the command line pointing to the failing argument,
+    /// usage line and a hint about the help flag
+    fn report<O, E>(&self, res: Result<T, Error>, stdout: &mut O, stderr: &mut E) -> RunOutcome<T>
+    where
+        O: std::io::Write,
+        E: std::io::Write,
+    {
+        let msg = match res {
+            Ok(t) => return RunOutcome::Parsed(t),
+            Err(Error::Stdout(msg)) => {
+                let _ = writeln!(stdout, "{}", msg);
+                return RunOutcome::Exit(0);
             }
-        }
+            Err(Error::Context(context)) => context.render(),
+            Err(Error::Stderr(msg)) => msg,
+            Err(Error::Missing(metas)) => format!("Expected {}", Meta::Or(metas)),
+        };
+        let _ = writeln!(stderr, "{}", msg);
+        RunOutcome::Exit(self.info.error_code)
+    }
+
+    /// Execute the [`OptionParser`] in a multicall binary, extract a parsed value or print some
+    /// diagnostic and exit
+    ///
+    /// Same as [`run`](OptionParser::run), but the name the program is invoked with can select
+    /// one of the top level commands: with `fmt` command binary symlinked as `mytool-fmt` or
+    /// `fmt` invocations `mytool-fmt a.rs` and `mytool fmt a.rs` are parsed the same way. The
+    /// program name is also used in usage lines of the help. See [`Args::multicall`] for details.
+    ///
+    /// ```no_run
+    /// # use bpaf::*;
+    /// let fmt = command("fmt", Some("Format files"), Info::default().for_parser(positional("FILE")));
+    /// let check = command("check", Some("Check files"), Info::default().for_parser(positional("FILE")));
+    /// let opt = Info::default().for_parser(construct!([fmt, check])).run_multicall();
+    /// # drop(opt)
+    /// ```
+    #[must_use]
+    pub fn run_multicall(self) -> T {
+        let mut argv = std::env::args_os();
+        let argv0 = argv.next().unwrap_or_default();
+        self.run_with_args(Args::from_os_strings(argv).multicall(argv0))
     }
 
     /// Execute the [`OptionParser`] and produce a value that can be used in unit tests
     ///
     /// ```
-    /// #[test]
-    /// fn positional_argument() {
-    ///     let p = positional("FILE").help("File to process");
-    ///     let parser = Info::default().for_parser(p);
-    ///
-    ///     let help = parser
-    ///         .run_inner(Args::from(&["--help"]))
-    ///         .unwrap_err()
-    ///         .unwrap_stdout();
-    ///     let expected_help = "\
+    /// # use bpaf::*;
+    /// let p = Positional::new("FILE").help("File to use").string();
+    /// let parser = Info::default().for_parser(p);
+    ///
+    /// let help = parser
+    ///     .run_inner(Args::from(&["--help"]))
+    ///     .unwrap_err()
+    ///     .unwrap_stdout();
+    /// let expected_help = "\
     /// Usage: <FILE>
     ///
+    /// Available positional items:
+    ///     <FILE>  File to use
+    ///
     /// Available options:
     ///     -h, --help   Prints help information
     /// ";
-    ///     assert_eq!(expected_help, help);
-    /// }
+    /// assert_eq!(expected_help, help);
     /// ```
     ///
     /// See also [`Args`] and it's `From` impls to produce input and
@@ -693,18 +1010,73 @@ impl<T> OptionParser<T> {
     /// you'll know what to use: `unwrap_stdout` if you want to test generated help or `unwrap_stderr`
     /// if you are testing `parse` / `guard` / missing parameters.
     ///
+    /// Error messages don't include the command line and usage line [`run`](OptionParser::run)
+    /// adds to them, messages about missing items end with a hint about the help flag.
+    ///
     /// Exact string reperentations may change between versions including minor releases.
     pub fn run_inner(self, args: Args) -> Result<T, ParseFailure> {
-        match (self.parse)(args) {
-            Ok((t, rest)) if rest.is_empty() => Ok(t),
-            Ok((_, rest)) => Err(ParseFailure::Stderr(format!("unexpected {:?}", rest))),
-            Err(Error::Missing(metas)) => Err(ParseFailure::Stderr(format!(
+        self.run_args(args)
+    }
+
+    /// Execute the [`OptionParser`] on a line of text, for interactive consoles
+    ///
+    /// Line is split into words following shell quoting rules, see
+    /// [`Args::from_shell_str`]. Parser doesn't print anything and doesn't exit the process:
+    /// help and version information is returned as [`ParseFailure::Stdout`], parsing errors
+    /// and unterminated quotes as [`ParseFailure::Stderr`]. Parser can be used for any number
+    /// of lines.
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// let name = positional("NAME");
+    /// let add = command("add", Some("Add a name"), Info::default().for_parser(name));
+    /// let parser = Info::default().for_parser(add);
+    ///
+    /// assert_eq!(parser.run_line("add 'Jane Doe'").unwrap(), "Jane Doe");
+    /// let help = parser.run_line("add --help").unwrap_err().unwrap_stdout();
+    /// assert!(help.starts_with("Usage: <NAME>"));
+    /// let err = parser.run_line("add \"Jane").unwrap_err().unwrap_stderr();
+    /// assert_eq!(err, "unterminated double quote starting at line 1, column 5");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Same as with [`run_inner`](OptionParser::run_inner)
+    pub fn run_line(&self, line: &str) -> Result<T, ParseFailure> {
+        self.run_args(Args::from_shell_str(line).map_err(ParseFailure::Stderr)?)
+    }
+
+    /// Parse arguments, parsing failures come with their context
+    fn parse_args(&self, args: Args) -> Result<T, Error> {
+        let name = args.name.clone();
+        let err = match (self.parse)(args) {
+            Ok((t, rest)) if rest.is_empty() => return Ok(t),
+            Ok((_, rest)) => Error::Stderr(format!("unexpected {:?}", rest)),
+            Err(err) => err,
+        };
+        Err(self
+            .info
+            .error_context(&self.parser_meta, name.as_deref(), err))
+    }
+
+    /// Parse arguments, parsing failures contain only the error message with a hint about the
+    /// help flag for missing items
+    fn run_args(&self, args: Args) -> Result<T, ParseFailure> {
+        self.parse_args(args).map_err(|err| match err {
+            Error::Stdout(msg) => ParseFailure::Stdout(msg),
+            Error::Stderr(msg) => ParseFailure::Stderr(msg),
+            Error::Context(context) => match (context.missing, context.help_flag) {
+                (true, Some(help)) => ParseFailure::Stderr(format!(
+                    "{}, pass {} for usage information",
+                    context.message, help
+                )),
+                _ => ParseFailure::Stderr(context.message),
+            },
+            Error::Missing(metas) => ParseFailure::Stderr(format!(
                 "Expected {}, pass --help for usage information",
                 Meta::Or(metas)
-            ))),
-            Err(Error::Stdout(stdout)) => Err(ParseFailure::Stdout(stdout)),
-            Err(Error::Stderr(stderr)) => Err(ParseFailure::Stderr(stderr)),
-        }
+            )),
+        })
     }
 }
 
@@ -54,10 +54,10 @@
 //!
 use std::ffi::OsString;
 
-use super::{Args, Error, Item, OptionParser, Parser, Rc};
+use super::{Args, Error, Item, MaybeSendSync, OptionParser, Parser, Rc};
 use crate::{
     args::{Arg, Word},
-    info::{ItemKind, Meta},
+    info::{ItemKind, Meta, SubparserMeta},
 };
 
 /// A named thing used to create Flag, Switch or Argument.
@@ -66,6 +66,7 @@ pub struct Named {
     short: Vec<char>,
     long: Vec<&'static str>,
     help: Option<String>,
+    long_help: Option<String>,
 }
 
 /// A flag/switch/argument that has a short name
@@ -89,6 +90,7 @@ pub fn short(short: char) -> Named {
         short: vec![short],
         long: Vec::new(),
         help: None,
+        long_help: None,
     }
 }
 
@@ -113,6 +115,7 @@ pub fn long(long: &'static str) -> Named {
         short: Vec::new(),
         long: vec![long],
         help: None,
+        long_help: None,
     }
 }
 
@@ -179,6 +182,33 @@ impl Named {
         self
     }
 
+    /// Add a detailed help message to a flag/switch/argument
+    ///
+    /// Detailed message replaces the message set with [`help`](Named::help) when help is
+    /// requested with a long name, `--help`, a short name such as `-h` shows the short one.
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// let jobs = short('j')
+    ///     .long("jobs")
+    ///     .help("Number of parallel jobs")
+    ///     .long_help("Number of parallel jobs\n\nDefaults to the number of CPUs")
+    ///     .argument("N");
+    /// let parser = Info::default().for_parser(jobs);
+    /// let short = parser.run_line("-h").unwrap_err().unwrap_stdout();
+    /// assert!(short.contains("Number of parallel jobs\n"));
+    /// let long = parser.run_line("--help").unwrap_err().unwrap_stdout();
+    /// assert!(long.contains("Defaults to the number of CPUs"));
+    /// ```
+    #[must_use]
+    pub fn long_help<M>(mut self, help: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.long_help = Some(help.into());
+        self
+    }
+
     /// Simple boolean flag
     ///
     /// Parser produces `true` if flag is present in a command line or `false` otherwise
@@ -193,7 +223,10 @@ impl Named {
     /// ```
     #[must_use]
     pub fn switch(self) -> Parser<bool> {
-        build_flag_parser(true, Some(false), self.short, self.long, self.help)
+        with_long_help(
+            build_flag_parser(true, Some(false), self.short, self.long, self.help),
+            self.long_help,
+        )
     }
 
     /// Flag with custom present/absent values
@@ -216,9 +249,12 @@ impl Named {
     #[must_use]
     pub fn flag<T>(self, present: T, absent: T) -> Parser<T>
     where
-        T: Clone + 'static,
+        T: Clone + MaybeSendSync + 'static,
     {
-        build_flag_parser(present, Some(absent), self.short, self.long, self.help)
+        with_long_help(
+            build_flag_parser(present, Some(absent), self.short, self.long, self.help),
+            self.long_help,
+        )
     }
 
     /// Required flag with custom value
@@ -251,9 +287,12 @@ impl Named {
     #[must_use]
     pub fn req_flag<T>(self, present: T) -> Parser<T>
     where
-        T: Clone + 'static,
+        T: Clone + MaybeSendSync + 'static,
     {
-        build_flag_parser(present, None, self.short, self.long, self.help)
+        with_long_help(
+            build_flag_parser(present, None, self.short, self.long, self.help),
+            self.long_help,
+        )
     }
 
     /// Named argument that can be encoded as String
@@ -269,8 +308,11 @@ impl Named {
     /// ```
     #[must_use]
     pub fn argument(self, metavar: &'static str) -> Parser<String> {
-        build_argument(self.short, self.long, self.help, metavar)
-            .parse(|x| x.utf8.ok_or("not utf8")) // TODO - provide a better diagnostic
+        with_long_help(
+            build_argument(self.short, self.long, self.help, metavar),
+            self.long_help,
+        )
+        .parse(|x| x.utf8.ok_or("not utf8")) // TODO - provide a better diagnostic
     }
 
     /// Named argument in OS specific encoding
@@ -286,7 +328,11 @@ impl Named {
     /// ```
     #[must_use]
     pub fn argument_os(self, metavar: &'static str) -> Parser<OsString> {
-        build_argument(self.short, self.long, self.help, metavar).map(|x| x.os)
+        with_long_help(
+            build_argument(self.short, self.long, self.help, metavar),
+            self.long_help,
+        )
+        .map(|x| x.os)
     }
 }
 
@@ -299,7 +345,7 @@ impl Named {
 /// ```
 #[must_use]
 pub fn positional(metavar: &'static str) -> Parser<String> {
-    build_positional(metavar).parse(|x| x.utf8.ok_or("not utf8")) // TODO - provide a better diagnostic
+    Positional::new(metavar).string()
 }
 
 /// Positional argument that can be encoded as String and will be taken only if check passes
@@ -313,7 +359,7 @@ pub fn positional(metavar: &'static str) -> Parser<String> {
 /// ```
 pub fn positional_if<F>(metavar: &'static str, check: F) -> Parser<Option<String>>
 where
-    F: Fn(&str) -> bool + 'static,
+    F: Fn(&str) -> bool + MaybeSendSync + 'static,
 {
     let check = move |w: &Word| match &w.utf8 {
         Some(s) => check(s),
@@ -337,7 +383,220 @@ where
 /// ```
 #[must_use]
 pub fn positional_os(metavar: &'static str) -> Parser<OsString> {
-    build_positional(metavar).map(|x| x.os)
+    Positional::new(metavar).os()
+}
+
+/// Positional argument with a help message
+///
+/// ```rust
+/// # use bpaf::*;
+/// let file: Parser<String> = Positional::new("FILE").help("File to process").string();
+/// # drop(file)
+/// ```
+#[derive(Clone, Debug)]
+pub struct Positional {
+    metavar: &'static str,
+    help: Option<String>,
+    long_help: Option<String>,
+}
+
+impl Positional {
+    /// Create a positional argument with a given metavar
+    #[must_use]
+    pub const fn new(metavar: &'static str) -> Self {
+        Self {
+            metavar,
+            help: None,
+            long_help: None,
+        }
+    }
+
+    /// Add a help message to a positional argument
+    #[must_use]
+    pub fn help<M>(mut self, help: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Add a detailed help message to a positional argument, see [`Named::long_help`]
+    #[must_use]
+    pub fn long_help<M>(mut self, help: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.long_help = Some(help.into());
+        self
+    }
+
+    /// Positional argument that can be encoded as String, see [`positional`]
+    #[must_use]
+    pub fn string(self) -> Parser<String> {
+        with_long_help(build_positional(self.metavar, self.help), self.long_help)
+            .parse(|x| x.utf8.ok_or("not utf8"))
+        // TODO - provide a better diagnostic
+    }
+
+    /// Positional argument in OS specific encoding, see [`positional_os`]
+    #[must_use]
+    pub fn os(self) -> Parser<OsString> {
+        with_long_help(build_positional(self.metavar, self.help), self.long_help).map(|x| x.os)
+    }
+}
+
+/// A fixed set of string values an argument can take, see [`Parser::values`]
+///
+/// ```rust
+/// # use bpaf::*;
+/// #[derive(Debug, Clone)]
+/// enum Format {
+///     Text,
+///     Markdown,
+/// }
+/// let values = Values::new()
+///     .value("text", Format::Text)
+///     .help("Plain text")
+///     .value("markdown", Format::Markdown)
+///     .alias("md")
+///     .help("Markdown with links")
+///     .ignore_case();
+/// let format: Parser<Format> = long("format").argument("FORMAT").values(values);
+/// # drop(format)
+/// ```
+#[derive(Clone, Debug)]
+pub struct Values<T> {
+    values: Vec<Value<T>>,
+    ignore_case: bool,
+}
+
+#[derive(Clone, Debug)]
+struct Value<T> {
+    name: &'static str,
+    aliases: Vec<&'static str>,
+    help: Option<String>,
+    value: T,
+}
+
+impl<T> Default for Values<T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            ignore_case: false,
+        }
+    }
+}
+
+impl<T> Values<T> {
+    /// Create an empty set of values
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a value with a name
+    #[must_use]
+    pub fn value(mut self, name: &'static str, value: T) -> Self {
+        self.values.push(Value {
+            name,
+            aliases: Vec::new(),
+            help: None,
+            value,
+        });
+        self
+    }
+
+    /// Add a hidden alias to the last added value
+    ///
+    /// # Panics
+    ///
+    /// Will panic if no values were added yet
+    #[must_use]
+    pub fn alias(mut self, alias: &'static str) -> Self {
+        self.last().aliases.push(alias);
+        self
+    }
+
+    /// Add a help message to the last added value
+    ///
+    /// # Panics
+    ///
+    /// Will panic if no values were added yet
+    #[must_use]
+    pub fn help<M>(mut self, help: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.last().help = Some(help.into());
+        self
+    }
+
+    /// Compare names and aliases ignoring the case
+    #[must_use]
+    pub fn ignore_case(mut self) -> Self {
+        self.ignore_case = true;
+        self
+    }
+
+    /// Name of a value, used to turn parsed values back into arguments
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// let values = Values::new().value("fast", 1).value("slow", 2);
+    /// assert_eq!(values.name_of(&2), Some("slow"));
+    /// ```
+    #[must_use]
+    pub fn name_of(&self, value: &T) -> Option<&'static str>
+    where
+        T: PartialEq,
+    {
+        self.values
+            .iter()
+            .find(|v| &v.value == value)
+            .map(|v| v.name)
+    }
+
+    fn last(&mut self) -> &mut Value<T> {
+        self.values
+            .last_mut()
+            .expect("Add a value before adding an alias or a help to it")
+    }
+
+    pub(crate) fn find(&self, input: &str) -> Option<&T> {
+        let matches = |name: &str| {
+            if self.ignore_case {
+                name.to_lowercase() == input.to_lowercase()
+            } else {
+                name == input
+            }
+        };
+        self.values
+            .iter()
+            .find(|v| matches(v.name) || v.aliases.iter().any(|a| matches(a)))
+            .map(|v| &v.value)
+    }
+
+    pub(crate) fn names(&self) -> String {
+        self.values
+            .iter()
+            .map(|v| v.name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Help lines listing all the values, one per line
+    pub(crate) fn help_lines(&self) -> String {
+        let width = self.values.iter().map(|v| v.name.len()).max().unwrap_or(0);
+        self.values
+            .iter()
+            .map(|v| match &v.help {
+                Some(help) => format!("{:width$}  {}", v.name, help, width = width),
+                None => v.name.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
 /// Subcommand parser
@@ -380,28 +639,280 @@ where
     T: 'static,
     M: Into<String>,
 {
+    let cmd = Command::new(name);
+    match help {
+        Some(help) => cmd.help(help),
+        None => cmd,
+    }
+    .for_parser(subparser)
+}
+
+/// Subcommand parser with aliases, see [`command`] for details
+///
+/// ```rust
+/// # use bpaf::*;
+/// let force = short('f').long("force").switch();
+/// let remove = Info::default().descr("Remove a file").for_parser(force);
+///
+/// // accepts `remove`, `rm` and deprecated `delete`, as well as unique prefixes
+/// // of `remove` and `rm` such as `rem`
+/// let remove: Parser<bool> = Command::new("remove")
+///     .help("Remove a file")
+///     .alias("rm")
+///     .hidden_alias("delete")
+///     .allow_prefix()
+///     .for_parser(remove);
+/// # drop(remove);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Command {
+    name: &'static str,
+    help: Option<String>,
+    aliases: Vec<&'static str>,
+    hidden_aliases: Vec<&'static str>,
+    allow_prefix: bool,
+    default: bool,
+}
+
+impl Command {
+    /// Create a command with a given name
+    #[must_use]
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            help: None,
+            aliases: Vec::new(),
+            hidden_aliases: Vec::new(),
+            allow_prefix: false,
+            default: false,
+        }
+    }
+
+    /// Add a help message to a command
+    #[must_use]
+    pub fn help<M>(mut self, help: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Add an alias, aliases are listed along with the command name in the help
+    #[must_use]
+    pub fn alias(mut self, alias: &'static str) -> Self {
+        self.aliases.push(alias);
+        self
+    }
+
+    /// Add an alias that is accepted but not listed in the help
+    #[must_use]
+    pub fn hidden_alias(mut self, alias: &'static str) -> Self {
+        self.hidden_aliases.push(alias);
+        self
+    }
+
+    /// Accept a prefix of the name or a visible alias
+    ///
+    /// Prefix is accepted only if no other command at the same level starts with it.
+    #[must_use]
+    pub fn allow_prefix(mut self) -> Self {
+        self.allow_prefix = true;
+        self
+    }
+
+    /// Run this command when no command is given
+    ///
+    /// Subparser runs on the remaining arguments so its flags are accepted without the command
+    /// name, anything it doesn't consume is left for the outer parser. The command doesn't run
+    /// if the next item is a name of another command at the same level. Usage shows the
+    /// command as optional.
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// let short = short('s').long("short").switch();
+    /// let status = Info::default().descr("Show the status").for_parser(short);
+    /// // `prog`, `prog -s`, `prog status` and `prog status -s` are all accepted, with
+    /// // `construct!(status, verbose)` so are `prog -v` and `prog -s -v`
+    /// let status = Command::new("status").default_command().for_parser(status);
+    /// # drop(status);
+    /// ```
+    #[must_use]
+    pub fn default_command(mut self) -> Self {
+        self.default = true;
+        self
+    }
+
+    /// Use this command to run a subparser
+    #[must_use]
+    pub fn for_parser<T>(self, subparser: OptionParser<T>) -> Parser<T>
+    where
+        T: 'static,
+    {
+        let Command {
+            name,
+            help,
+            mut aliases,
+            hidden_aliases,
+            allow_prefix,
+            default,
+        } = self;
+        let item = Meta::from(Item {
+            short: None,
+            long: Some(name),
+            metavar: None,
+            help,
+            kind: ItemKind::Command,
+            aliases: aliases.clone(),
+            hidden_aliases: hidden_aliases.clone(),
+            short_aliases: Vec::new(),
+            subparser: Some(Rc::new(SubparserMeta {
+                parser_meta: subparser.parser_meta.clone(),
+                help_meta: subparser.help_meta.clone(),
+                info: subparser.info.clone(),
+            })),
+            default: None,
+            long_help: None,
+            section: None,
+        });
+        let meta = if default { item.optional() } else { item };
+        let meta2 = meta.clone();
+        aliases.insert(0, name);
+        let parse = move |mut args: Args| {
+            let (commands, hidden_commands) = (args.commands.clone(), args.hidden_commands.clone());
+            let prog = args.name.clone();
+            // once the command is given, missing items are reported in its context
+            let entered = |args: Args| {
+                let name = args.name.clone();
+                (subparser.parse)(args).map_err(|err| match err {
+                    err @ Error::Missing(_) => {
+                        let (info, meta) = (&subparser.info, &subparser.parser_meta);
+                        info.error_context(meta, name.as_deref(), err)
+                    }
+                    err => err,
+                })
+            };
+            let (t, mut args) = if let Some(selected) = args.selected {
+                if selected != name {
+                    return Err(Error::Missing(vec![meta2.clone()]));
+                }
+                args.enter_command(None);
+                entered(args)?
+            } else if aliases
+                .iter()
+                .chain(&hidden_aliases)
+                .any(|n| args.take_cmd(n))
+                || (allow_prefix && args.take_cmd_prefix(&aliases, &hidden_aliases))
+            {
+                args.enter_command(Some(name));
+                entered(args)?
+            } else if default && !args.peek_is_command() {
+                // help and version for the default command are only available by its name,
+                // otherwise they would replace the help for the outer parser. Items not
+                // consumed by the default command are left for the outer parser. Names of
+                // other commands are left for them
+                (subparser.inner)(args)?
+            } else {
+                return Err(Error::Missing(vec![meta2.clone()]));
+            };
+            args.commands = commands;
+            args.hidden_commands = hidden_commands;
+            args.name = prog;
+            Ok((t, args))
+        };
+
+        Parser {
+            parse: Rc::new(parse),
+            meta,
+        }
+    }
+}
+
+/// Unknown command along with all the remaining arguments, git style plugins
+///
+/// Parser takes the first remaining item if it's a word, and all the original arguments after
+/// it, and succeeds only if no other command at the same level matches. A word that looks like
+/// a typo in a name of a known command is rejected with a suggestion. Since this parser consumes
+/// everything - it should go last.
+///
+/// ```rust
+/// # use bpaf::*;
+/// # use std::ffi::OsString;
+/// #[derive(Debug, Clone)]
+/// enum Cmd {
+///     Check(bool),
+///     External(String, Vec<OsString>),
+/// }
+/// let ws = long("workspace").switch();
+/// let check = command("check", Some("Check a package"), Info::default().for_parser(ws))
+///     .map(Cmd::Check);
+/// let external = external_command().map(|(name, args)| Cmd::External(name, args));
+/// // `prog foo --bar` produces `Cmd::External("foo", ["--bar"])` so program can run `prog-foo`
+/// let cmd = construct!([check, external]);
+/// # drop(cmd);
+/// ```
+#[must_use]
+pub fn external_command() -> Parser<(String, Vec<OsString>)> {
     let meta = Meta::from(Item {
         short: None,
-        long: Some(name),
+        long: None,
         metavar: None,
-        help: help.map(Into::into),
+        help: Some("External command".to_owned()),
         kind: ItemKind::Command,
+        aliases: Vec::new(),
+        hidden_aliases: Vec::new(),
+        short_aliases: Vec::new(),
+        subparser: None,
+        default: None,
+        long_help: None,
+        section: None,
     });
     let meta2 = meta.clone();
     let parse = move |mut args: Args| {
-        if args.take_cmd(name) {
-            (subparser.parse)(args)
-        } else {
-            Err(Error::Missing(vec![meta2.clone()]))
+        let first = args.items_iter().next().map(|(ix, _)| ix);
+        let (name, rest) = match args.take_word_and_rest() {
+            Some(cmd) => cmd,
+            None => return Err(Error::Missing(vec![meta2.clone()])),
+        };
+        if args.commands.contains(&name.as_str()) {
+            return Err(Error::Missing(vec![meta2.clone()]));
         }
+        if let Some(cmd) = args
+            .commands
+            .iter()
+            .find(|cmd| edit_distance(cmd, &name) <= cmd.len() / 3)
+        {
+            let message = format!("No such command: `{}`, did you mean `{}`?", name, cmd);
+            return Err(match first {
+                Some(ix) => args.error_at(ix, message),
+                None => Error::Stderr(message),
+            });
+        }
+        // lose to any other parser that succeeds
+        args.head = usize::MAX;
+        Ok(((name, rest), args))
     };
-
     Parser {
         parse: Rc::new(parse),
         meta,
     }
 }
 
+/// Levenshtein distance between two strings
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let subst = prev[j] + usize::from(ca != *cb);
+            cur[j + 1] = subst.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
 fn short_or_long_flag(arg: &Arg, shorts: &[char], longs: &[&str]) -> bool {
     shorts.iter().any(|&c| arg.is_short(c)) || longs.iter().any(|s| arg.is_long(s))
 }
@@ -414,7 +925,7 @@ fn build_flag_parser<T>(
     help: Option<String>,
 ) -> Parser<T>
 where
-    T: Clone + 'static,
+    T: Clone + MaybeSendSync + 'static,
 {
     let item = Item {
         short: shorts.first().copied(),
@@ -422,6 +933,13 @@ where
         metavar: None,
         help,
         kind: ItemKind::Flag,
+        aliases: Vec::new(),
+        hidden_aliases: longs.iter().skip(1).copied().collect(),
+        short_aliases: shorts.iter().skip(1).copied().collect(),
+        subparser: None,
+        default: None,
+        long_help: None,
+        section: None,
     };
     let required = absent.is_none();
     let meta = item.required(required);
@@ -460,6 +978,13 @@ fn build_argument(
         long: longs.first().copied(),
         metavar: Some(metavar),
         help,
+        aliases: Vec::new(),
+        hidden_aliases: longs.iter().skip(1).copied().collect(),
+        short_aliases: shorts.iter().skip(1).copied().collect(),
+        subparser: None,
+        default: None,
+        long_help: None,
+        section: None,
     };
     let meta = item.required(true);
     let meta2 = meta.clone();
@@ -478,13 +1003,34 @@ fn build_argument(
     }
 }
 
-fn build_positional(metavar: &'static str) -> Parser<Word> {
+/// Attach a detailed help message to items of a parser
+fn with_long_help<T>(parser: Parser<T>, long_help: Option<String>) -> Parser<T> {
+    match long_help {
+        Some(long_help) => Parser {
+            parse: parser.parse,
+            meta: parser.meta.map_items(&|item| Item {
+                long_help: Some(long_help.clone()),
+                ..item
+            }),
+        },
+        None => parser,
+    }
+}
+
+fn build_positional(metavar: &'static str, help: Option<String>) -> Parser<Word> {
     let item = Item {
         short: None,
         long: None,
         metavar: Some(metavar),
-        help: None,
+        help,
         kind: ItemKind::Positional,
+        aliases: Vec::new(),
+        hidden_aliases: Vec::new(),
+        short_aliases: Vec::new(),
+        subparser: None,
+        default: None,
+        long_help: None,
+        section: None,
     };
     let meta = item.required(true);
     let meta2 = meta.clone();
@@ -501,7 +1047,7 @@ fn build_positional(metavar: &'static str) -> Parser<Word> {
 
 fn build_positional_if<F>(metavar: &'static str, check: F) -> Parser<Option<Word>>
 where
-    F: Fn(&Word) -> bool + 'static,
+    F: Fn(&Word) -> bool + MaybeSendSync + 'static,
 {
     let item = Item {
         short: None,
@@ -509,6 +1055,13 @@ where
         metavar: Some(metavar),
         help: None,
         kind: ItemKind::Positional,
+        aliases: Vec::new(),
+        hidden_aliases: Vec::new(),
+        short_aliases: Vec::new(),
+        subparser: None,
+        default: None,
+        long_help: None,
+        section: None,
     };
     let meta = item.required(false);
     let meta2 = meta.clone();
@@ -0,0 +1,565 @@
+//! Helpers for testing parsers, enabled by `testing` feature
+//!
+//! Outcomes of parsing are rendered as strings so they can be compared against tables of
+//! expected results or stored in snapshot files:
+//!
+//! ```rust
+//! # use bpaf::*;
+//! use bpaf::testing::check_table;
+//! let verbose = short('v').switch();
+//! let parser = Info::default().for_parser(verbose);
+//! check_table(&parser, &[
+//!     (&[], "false"),
+//!     (&["-v"], "true"),
+//!     (&["-x"], "stderr: -x is not expected in this context"),
+//! ]);
+//! ```
+//!
+//! Snapshot files are compared with [`assert_snapshot`], set `BPAF_BLESS` environment variable
+//! to write current values instead.
+use std::fmt::Debug;
+use std::path::Path;
+
+use crate::info::{ItemKind, Meta};
+use crate::{Args, OptionParser, ParseFailure, Unparse};
+
+/// Environment variable that makes [`assert_snapshot`] update snapshot files
+pub const BLESS_VAR: &str = "BPAF_BLESS";
+
+/// Run the parser and render the outcome as a string
+///
+/// Successful results are rendered with [`Debug`], failures as `stdout: ...` or `stderr: ...`
+/// ```rust
+/// # use bpaf::*;
+/// use bpaf::testing::outcome;
+/// let parser = Info::default().for_parser(short('n').argument("N").from_str::<u32>());
+/// assert_eq!(outcome(&parser, &["-n", "3"]), "3");
+/// ```
+#[must_use]
+pub fn outcome<T>(parser: &OptionParser<T>, args: &[&str]) -> String
+where
+    T: Clone + Debug,
+{
+    match parser.clone().run_inner(Args::from(args)) {
+        Ok(t) => format!("{:?}", t),
+        Err(ParseFailure::Stdout(msg)) => format!("stdout: {}", msg),
+        Err(ParseFailure::Stderr(msg)) => format!("stderr: {}", msg),
+    }
+}
+
+/// Run the parser over every argument list and compare outcomes with expected ones
+///
+/// See [`outcome`] for the format of expected values.
+///
+/// # Panics
+/// Panics listing every mismatch if any of the outcomes differ from expected ones
+#[track_caller]
+pub fn check_table<T>(parser: &OptionParser<T>, cases: &[(&[&str], &str)])
+where
+    T: Clone + Debug,
+{
+    let mut failures = String::new();
+    for (args, expected) in cases {
+        let actual = outcome(parser, args);
+        if &actual != expected {
+            failures.push_str(&format!(
+                "for {:?}\nexpected: {}\n  actual: {}\n",
+                args, expected, actual
+            ));
+        }
+    }
+    if !failures.is_empty() {
+        panic!("Unexpected outcomes:\n{}", failures);
+    }
+}
+
+/// Render help messages for the parser and every command in it, recursively
+///
+/// Result contains pairs of command paths starting with `name` and help messages
+/// ```rust
+/// # use bpaf::*;
+/// use bpaf::testing::all_help;
+/// let add = command("add", Some("Add a file"), Info::default().for_parser(positional("FILE")));
+/// let parser = Info::default().for_parser(add);
+/// let help = all_help(&parser, "app");
+/// assert_eq!(help[1].0, "app add");
+/// ```
+#[must_use]
+pub fn all_help<T>(parser: &OptionParser<T>, name: &str) -> Vec<(String, String)> {
+    parser
+        .sections(name)
+        .into_iter()
+        .map(|section| {
+            let help = section
+                .info
+                .render_help(section.parser_meta, section.help_meta, None)
+                .expect("Couldn't render help");
+            (section.path.join(" "), help)
+        })
+        .collect()
+}
+
+/// Help messages for the parser and every command in it as a single string
+///
+/// Each message is preceded by a `==> path <==` line, convenient for storing as a snapshot
+#[must_use]
+pub fn all_help_text<T>(parser: &OptionParser<T>, name: &str) -> String {
+    all_help(parser, name)
+        .into_iter()
+        .map(|(path, help)| format!("==> {} <==\n{}", path, help))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Compare `actual` with contents of a snapshot file
+///
+/// If `BPAF_BLESS` environment variable is set to a non empty value snapshot file is
+/// written instead, along with missing parent directories.
+///
+/// ```rust,no_run
+/// # use bpaf::*;
+/// use bpaf::testing::{all_help_text, assert_snapshot};
+/// let parser = Info::default().for_parser(short('v').switch());
+/// assert_snapshot("tests/snapshots/help.txt", &all_help_text(&parser, "app"));
+/// ```
+///
+/// # Panics
+/// Panics if the snapshot is missing or differs from `actual`, or if it can't be written
+#[track_caller]
+pub fn assert_snapshot<P: AsRef<Path>>(path: P, actual: &str) {
+    let path = path.as_ref();
+    if matches!(std::env::var_os(BLESS_VAR), Some(v) if !v.is_empty()) {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .unwrap_or_else(|e| panic!("Couldn't create {}: {}", parent.display(), e));
+        }
+        std::fs::write(path, actual)
+            .unwrap_or_else(|e| panic!("Couldn't write {}: {}", path.display(), e));
+        return;
+    }
+    let expected = match std::fs::read_to_string(path) {
+        Ok(expected) => expected,
+        Err(e) => panic!(
+            "Couldn't read snapshot {}: {}, run with {}=1 to create it",
+            path.display(),
+            e,
+            BLESS_VAR
+        ),
+    };
+    if expected != actual {
+        let mut diff = String::new();
+        let (exp, act) = (
+            expected.lines().collect::<Vec<_>>(),
+            actual.lines().collect::<Vec<_>>(),
+        );
+        for ix in 0..exp.len().max(act.len()) {
+            match (exp.get(ix), act.get(ix)) {
+                (Some(e), Some(a)) if e == a => {}
+                (e, a) => diff.push_str(&format!(
+                    "line {}\n-{}\n+{}\n",
+                    ix + 1,
+                    e.unwrap_or(&""),
+                    a.unwrap_or(&"")
+                )),
+            }
+        }
+        panic!(
+            "Snapshot {} differs, run with {}=1 to update it\n{}",
+            path.display(),
+            BLESS_VAR,
+            diff
+        );
+    }
+}
+
+/// A problem found by [`Fuzz`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FuzzFailure {
+    /// Arguments that caused the problem
+    pub args: Vec<String>,
+    /// Seed of the run, pass it to [`Fuzz::seed`] to reproduce the problem
+    pub seed: u64,
+    /// What happened
+    pub kind: FuzzFailureKind,
+}
+
+/// Kind of [`FuzzFailure`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FuzzFailureKind {
+    /// Parsing panicked with this message
+    Panic(String),
+    /// Arguments generated from the parser structure were rejected with this message
+    Rejected(String),
+    /// Value parsed from generated arguments, turned back into arguments with [`Unparse`],
+    /// parsed into a different value or failed with this message
+    RoundTrip(String),
+}
+
+impl std::fmt::Display for FuzzFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            FuzzFailureKind::Panic(msg) => {
+                write!(f, "parser panicked on {:?}: {}", self.args, msg)?
+            }
+            FuzzFailureKind::Rejected(msg) => {
+                write!(f, "parser rejected generated {:?}: {}", self.args, msg)?;
+            }
+            FuzzFailureKind::RoundTrip(msg) => {
+                write!(
+                    f,
+                    "value parsed from {:?} doesn't round trip: {}",
+                    self.args, msg
+                )?;
+            }
+        }
+        write!(f, " (seed {})", self.seed)
+    }
+}
+
+/// Small xorshift generator, good enough to pick items and not worth a dependency
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn coin(&mut self) -> bool {
+        self.next() & 1 == 0
+    }
+}
+
+/// Tokens that often confuse argument splitting
+const ODD_TOKENS: &[&str] = &["-", "--", "=", "", "-=", "--=", "-1", "--help", "-h=1"];
+
+/// Randomized checks for a parser
+///
+/// Generates command lines from the parser structure and checks that they are accepted, then
+/// mutates them by removing, duplicating, swapping, truncating and gluing arguments together and
+/// checks that parsing doesn't panic. Values for arguments and positional items are `1` unless
+/// [`values`](Fuzz::values) is used. For types implementing [`Unparse`]
+/// [`round_trip`](Fuzz::round_trip) also checks that values parsed from generated command lines
+/// survive turning them back into arguments and parsing again.
+///
+/// ```rust
+/// # use bpaf::*;
+/// use bpaf::testing::Fuzz;
+/// let name = long("name").argument("NAME");
+/// let verbose = long("verbose").switch();
+/// let parser = Info::default().for_parser(construct!(name, verbose));
+/// Fuzz::new(&parser).iterations(200).check();
+/// ```
+///
+/// With `cargo-fuzz` use the input to seed a single iteration:
+/// ```rust,ignore
+/// fuzz_target!(|data: &[u8]| {
+///     Fuzz::new(&parser()).seed_bytes(data).iterations(1).check();
+/// });
+/// ```
+pub struct Fuzz<'a, T> {
+    parser: &'a OptionParser<T>,
+    seed: u64,
+    iterations: usize,
+    check_valid: bool,
+    values: Box<dyn Fn(&str) -> String + 'a>,
+    round_trip: Option<RoundTripCheck<'a, T>>,
+}
+
+/// Checks that a parsed value parses back from its arguments, see [`Fuzz::round_trip`]
+type RoundTripCheck<'a, T> = Box<dyn Fn(&T) -> Result<(), String> + 'a>;
+
+impl<'a, T> Fuzz<'a, T>
+where
+    T: Clone + Debug,
+{
+    /// Create a fuzzer for a parser
+    #[must_use]
+    pub fn new(parser: &'a OptionParser<T>) -> Self {
+        Self {
+            parser,
+            seed: 0x5EED_B0A7,
+            iterations: 1000,
+            check_valid: true,
+            values: Box::new(|_| String::from("1")),
+            round_trip: None,
+        }
+    }
+
+    /// Set a seed, same seed gives the same command lines
+    #[must_use]
+    pub fn seed(mut self, seed: u64) -> Self {
+        // xorshift gets stuck on zero
+        self.seed = seed.max(1);
+        self
+    }
+
+    /// Derive a seed from arbitrary bytes, for use with external fuzzers
+    #[must_use]
+    pub fn seed_bytes(self, bytes: &[u8]) -> Self {
+        // FNV-1a
+        let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+        for b in bytes {
+            hash ^= u64::from(*b);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        self.seed(hash)
+    }
+
+    /// Number of command lines to generate, each one is also mutated
+    #[must_use]
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Produce values for arguments and positional items, function gets a metavariable
+    #[must_use]
+    pub fn values<F>(mut self, values: F) -> Self
+    where
+        F: Fn(&str) -> String + 'a,
+    {
+        self.values = Box::new(values);
+        self
+    }
+
+    /// Don't require generated command lines to be accepted, only check for panics
+    ///
+    /// Useful for parsers with guards or ambiguous structure
+    #[must_use]
+    pub fn panics_only(mut self) -> Self {
+        self.check_valid = false;
+        self
+    }
+
+    /// Check that values parsed from generated command lines parse back into the same values
+    /// after turning them into arguments with [`Unparse`]
+    ///
+    /// ```rust
+    /// # use bpaf::*;
+    /// use bpaf::testing::Fuzz;
+    /// #[derive(Debug, Clone, PartialEq)]
+    /// struct Opts(u32);
+    /// impl Unparse for Opts {
+    ///     fn unparse(&self, args: &mut UnparseArgs) {
+    ///         args.named("--jobs", self.0.to_string().into());
+    ///     }
+    /// }
+    /// let jobs = long("jobs").argument("N").from_str::<u32>().map(Opts);
+    /// let parser = Info::default().for_parser(jobs);
+    /// Fuzz::new(&parser).round_trip().check();
+    /// ```
+    #[must_use]
+    pub fn round_trip(mut self) -> Self
+    where
+        T: Unparse + PartialEq,
+    {
+        let parser = self.parser;
+        self.round_trip = Some(Box::new(move |value: &T| {
+            let args = value.to_args();
+            let argv = args.iter().map(|a| a.as_os_str()).collect::<Vec<_>>();
+            match parser.clone().run_inner(Args::from(argv.as_slice())) {
+                Ok(parsed) if parsed == *value => Ok(()),
+                Ok(parsed) => Err(format!(
+                    "{:?} parsed from {:?} as {:?}",
+                    value, args, parsed
+                )),
+                Err(ParseFailure::Stdout(msg) | ParseFailure::Stderr(msg)) => {
+                    Err(format!("{:?} rejected {:?}: {}", value, args, msg))
+                }
+            }
+        }));
+        self
+    }
+
+    /// Run the checks, returns the first problem found
+    ///
+    /// # Errors
+    /// Returns a problem if parser panics, rejects a generated command line or a parsed value
+    /// doesn't round trip
+    pub fn run(&self) -> Result<(), FuzzFailure> {
+        let mut rng = Rng(self.seed);
+        let shorts = self
+            .parser
+            .parser_meta
+            .flags()
+            .iter()
+            .filter_map(|i| i.short)
+            .collect::<Vec<_>>();
+        for _ in 0..self.iterations {
+            let mut args = Vec::new();
+            let valid = self.generate(&self.parser.parser_meta, &mut rng, &mut args);
+            let failure = |args: Vec<String>, kind| FuzzFailure {
+                args,
+                seed: self.seed,
+                kind,
+            };
+            match self.try_parse(&args) {
+                Err(msg) => return Err(failure(args, FuzzFailureKind::Panic(msg))),
+                Ok(Err(ParseFailure::Stderr(msg))) if valid && self.check_valid => {
+                    return Err(failure(args, FuzzFailureKind::Rejected(msg)));
+                }
+                Ok(Ok(value)) if valid => {
+                    if let Some(Err(msg)) = self.round_trip.as_ref().map(|check| check(&value)) {
+                        return Err(failure(args, FuzzFailureKind::RoundTrip(msg)));
+                    }
+                }
+                Ok(_) => {}
+            }
+
+            for _ in 0..=rng.below(3) {
+                mutate(&mut args, &shorts, &mut rng);
+            }
+            if let Err(msg) = self.try_parse(&args) {
+                return Err(failure(args, FuzzFailureKind::Panic(msg)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the checks
+    ///
+    /// # Panics
+    /// Panics with a description of the first problem found
+    #[track_caller]
+    pub fn check(&self) {
+        if let Err(failure) = self.run() {
+            panic!("{}", failure);
+        }
+    }
+
+    fn try_parse(&self, args: &[String]) -> Result<Result<T, ParseFailure>, String> {
+        let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+        let parser = self.parser.clone();
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            parser.run_inner(Args::from(args.as_slice()))
+        }))
+        .map_err(|payload| {
+            if let Some(msg) = payload.downcast_ref::<&str>() {
+                (*msg).to_owned()
+            } else if let Some(msg) = payload.downcast_ref::<String>() {
+                msg.clone()
+            } else {
+                String::from("unknown panic")
+            }
+        })
+    }
+
+    /// Generate a command line accepted by `meta`, returns false if that's not possible
+    fn generate(&self, meta: &Meta, rng: &mut Rng, res: &mut Vec<String>) -> bool {
+        match meta {
+            Meta::Empty => false,
+            Meta::Id => true,
+            Meta::And(xs) => xs.iter().all(|x| self.generate(x, rng, res)),
+            Meta::Or(xs) => {
+                let xs = xs
+                    .iter()
+                    .filter(|x| !matches!(x, Meta::Empty))
+                    .collect::<Vec<_>>();
+                !xs.is_empty() && self.generate(xs[rng.below(xs.len())], rng, res)
+            }
+            Meta::Required(x) | Meta::Decorated(x, _) | Meta::Hidden(x) => {
+                self.generate(x, rng, res)
+            }
+            Meta::Optional(x) => {
+                if rng.coin() {
+                    let len = res.len();
+                    if !self.generate(x, rng, res) {
+                        res.truncate(len);
+                    }
+                }
+                true
+            }
+            Meta::Many(x) => {
+                for _ in 0..rng.below(4) {
+                    let len = res.len();
+                    if !self.generate(x, rng, res) {
+                        res.truncate(len);
+                        break;
+                    }
+                }
+                true
+            }
+            Meta::Item(item) => match item.kind {
+                ItemKind::Flag => {
+                    let mut names = Vec::new();
+                    names.extend(item.short.iter().map(|s| format!("-{}", s)));
+                    names.extend(item.short_aliases.iter().map(|s| format!("-{}", s)));
+                    names.extend(item.long.iter().map(|l| format!("--{}", l)));
+                    names.extend(item.hidden_aliases.iter().map(|l| format!("--{}", l)));
+                    if names.is_empty() {
+                        return false;
+                    }
+                    res.push(names.swap_remove(rng.below(names.len())));
+                    if let Some(metavar) = item.metavar {
+                        res.push((self.values)(metavar));
+                    }
+                    true
+                }
+                ItemKind::Positional => {
+                    res.push((self.values)(item.positional_metavar()));
+                    true
+                }
+                ItemKind::Command => match (item.long, &item.subparser) {
+                    (Some(name), Some(sub)) => {
+                        let mut names = vec![name];
+                        names.extend(&item.aliases);
+                        names.extend(&item.hidden_aliases);
+                        res.push(names[rng.below(names.len())].to_owned());
+                        self.generate(&sub.parser_meta, rng, res)
+                    }
+                    _ => {
+                        res.push(String::from("external-command"));
+                        true
+                    }
+                },
+                ItemKind::Decor => true,
+            },
+        }
+    }
+}
+
+fn mutate(args: &mut Vec<String>, shorts: &[char], rng: &mut Rng) {
+    let len = args.len();
+    match rng.below(7) {
+        0 if len > 0 => {
+            args.remove(rng.below(len));
+        }
+        1 if len > 0 => {
+            let ix = rng.below(len);
+            args.insert(ix, args[ix].clone());
+        }
+        2 if len > 1 => args.swap(rng.below(len), rng.below(len)),
+        3 if len > 0 => {
+            // cut an argument at a random char boundary
+            let ix = rng.below(len);
+            let chars = args[ix].chars().collect::<Vec<_>>();
+            let cut = rng.below(chars.len() + 1);
+            args[ix] = chars[..cut].iter().collect();
+        }
+        4 if shorts.len() > 1 => {
+            // several short flags with a value: -ab=1
+            let a = shorts[rng.below(shorts.len())];
+            let b = shorts[rng.below(shorts.len())];
+            args.insert(rng.below(len + 1), format!("-{}{}=1", a, b));
+        }
+        5 if len > 1 => {
+            // glue a name and a value together: --name=value
+            let ix = rng.below(len - 1);
+            let next = args.remove(ix + 1);
+            args[ix] = format!("{}={}", args[ix], next);
+        }
+        _ => {
+            let token = ODD_TOKENS[rng.below(ODD_TOKENS.len())];
+            args.insert(rng.below(len + 1), token.to_owned());
+        }
+    }
+}
@@ -131,6 +131,31 @@ Available options:
     assert_eq!(expected_help, help);
 }
 
+#[test]
+fn alternatives_with_all_branches_hidden() {
+    let a = short('a').req_flag(1).hide();
+    let b = short('b').req_flag(2).hide();
+    let hidden = construct!([a, b]).group_help("Debug");
+    let c = short('c').req_flag(3);
+    let parser = Info::default().for_parser(construct!([hidden, c]));
+
+    let help = parser.run_line("--help").unwrap_err().unwrap_stdout();
+    assert!(help.starts_with("Usage: -c\n"), "{}", help);
+    let err = parser.run_line("").unwrap_err().unwrap_stderr();
+    assert_eq!(err, "Expected -c, pass --help for usage information");
+    assert_eq!(parser.run_line("-b").unwrap(), 2);
+
+    // a variant with only hidden fields
+    let a = short('a').req_flag(()).hide();
+    let b = short('b').req_flag(()).hide();
+    let both = construct!(a, b).map(|_| 1);
+    let c = short('c').req_flag(3);
+    let parser = Info::default().for_parser(construct!([both, c]));
+    let help = parser.run_line("--help").unwrap_err().unwrap_stdout();
+    assert!(help.starts_with("Usage: -c\n"), "{}", help);
+    assert_eq!(parser.run_line("-a -b").unwrap(), 1);
+}
+
 #[test]
 fn either_of_three_required_flags() {
     let a = short('a').req_flag(());
@@ -391,7 +416,7 @@ fn group_help() {
 Usage: [-a] [-b] [-c]
 
 Available options:
-                 Explanation applicable for both A and B
+  Explanation applicable for both A and B
     -a           flag A, related to B
     -b           flag B, related to A
 
@@ -525,12 +550,46 @@ fn positional_argument() {
     let expected_help = "\
 Usage: <FILE>
 
+Available positional items:
+    <FILE>  File to process
+
 Available options:
     -h, --help   Prints help information
 ";
     assert_eq!(expected_help, help);
 }
 
+#[test]
+fn positional_help() {
+    let src = Positional::new("SRC").help("Copy from").string();
+    let dst = Positional::new("DESTINATION")
+        .help("Copy to\nCreated if missing")
+        .os();
+    let parser = Info::default().for_parser(construct!(src, dst));
+
+    let help = parser
+        .clone()
+        .run_inner(Args::from(&["--help"]))
+        .unwrap_err()
+        .unwrap_stdout();
+    let expected_help = "\
+Usage: <SRC> <DESTINATION>
+
+Available positional items:
+    <SRC>          Copy from
+    <DESTINATION>  Copy to
+                   Created if missing
+
+Available options:
+    -h, --help   Prints help information
+";
+    assert_eq!(expected_help, help);
+
+    let (src, dst) = parser.run_inner(Args::from(&["a", "b"])).unwrap();
+    assert_eq!(src, "a");
+    assert_eq!(dst, "b");
+}
+
 mod git {
     use super::*;
 
@@ -632,6 +691,9 @@ fetches branches from remote repository
 
 Usage: [--dry_run] [--all] [<SRC>]
 
+Available positional items:
+    <SRC>
+
 Available options:
         --dry_run
         --all
@@ -654,6 +716,9 @@ add files to the staging area
 
 Usage: [-i] [--all] <FILE>...
 
+Available positional items:
+    <FILE>
+
 Available options:
     -i
         --all
@@ -796,3 +861,1686 @@ fn helpful_error_message() {
         .unwrap_stderr();
     assert_eq!("You need to specify at least one FOO", err);
 }
+
+#[test]
+fn long_prefix() {
+    fn connection() -> Parser<(String, u16)> {
+        let host = long("host").help("Host to connect to").argument("HOST");
+        let port = long("port").argument("PORT").from_str::<u16>();
+        construct!(host, port)
+    }
+    let db = connection().long_prefix("db");
+    let host = long("host").argument("HOST");
+    let parser = Info::default().for_parser(construct!(db, host));
+
+    let help = parser
+        .clone()
+        .run_inner(Args::from(&["--help"]))
+        .unwrap_err()
+        .unwrap_stdout();
+    let expected_help = "\
+Usage: --db-host HOST --db-port PORT --host HOST
+
+Available options:
+        --db-host <HOST>  Host to connect to
+        --db-port <PORT>
+        --host <HOST>
+    -h, --help            Prints help information
+";
+    assert_eq!(expected_help, help);
+
+    let res = parser
+        .clone()
+        .run_inner(Args::from(&[
+            "--host",
+            "a",
+            "--db-port",
+            "5432",
+            "--db-host",
+            "b",
+        ]))
+        .unwrap();
+    assert_eq!(res, (("b".to_owned(), 5432), "a".to_owned()));
+
+    let err = parser
+        .run_inner(Args::from(&[
+            "--host",
+            "a",
+            "--port",
+            "1",
+            "--db-host",
+            "b",
+        ]))
+        .unwrap_err()
+        .unwrap_stderr();
+    assert_eq!(
+        err,
+        "Expected --db-port PORT, pass --help for usage information"
+    );
+
+    // prefixed names are allocated once no matter how many times parser is created
+    let name = |p: Parser<(String, u16)>| p.meta.flags()[0].long.unwrap().as_ptr();
+    assert_eq!(
+        name(connection().long_prefix("db")),
+        name(connection().long_prefix("db"))
+    );
+}
+
+#[test]
+fn argument_values() {
+    let values = Values::new()
+        .value("fast", 1)
+        .help("Optimize for speed")
+        .value("small", 2)
+        .alias("tiny")
+        .value("none", 0)
+        .help("No optimizations");
+    let opt = long("opt")
+        .help("Optimization level")
+        .argument("OPT")
+        .values(values.clone());
+    let parser = Info::default().for_parser(opt);
+
+    let help = parser
+        .clone()
+        .run_inner(Args::from(&["--help"]))
+        .unwrap_err()
+        .unwrap_stdout();
+    let expected_help = "\
+Usage: --opt OPT
+
+Available options:
+        --opt <OPT>  Optimization level
+                     fast   Optimize for speed
+                     small
+                     none   No optimizations
+    -h, --help       Prints help information
+";
+    assert_eq!(expected_help, help);
+
+    let res = parser
+        .clone()
+        .run_inner(Args::from(&["--opt", "fast"]))
+        .unwrap();
+    assert_eq!(res, 1);
+
+    let res = parser
+        .clone()
+        .run_inner(Args::from(&["--opt", "tiny"]))
+        .unwrap();
+    assert_eq!(res, 2);
+
+    let err = parser
+        .run_inner(Args::from(&["--opt", "FAST"]))
+        .unwrap_err()
+        .unwrap_stderr();
+    assert_eq!(
+        err,
+        "Couldn't parse \"FAST\": expected one of fast, small, none"
+    );
+
+    let parser =
+        Info::default().for_parser(long("opt").argument("OPT").values(values.ignore_case()));
+    let res = parser.run_inner(Args::from(&["--opt", "Small"])).unwrap();
+    assert_eq!(res, 2);
+}
+
+#[test]
+fn command_aliases() {
+    let remove = Command::new("remove")
+        .help("Remove a file")
+        .alias("rm")
+        .hidden_alias("delete")
+        .allow_prefix()
+        .for_parser(Info::default().for_parser(Parser::pure('r')));
+    let rename = Command::new("rename")
+        .help("Rename a file")
+        .allow_prefix()
+        .for_parser(Info::default().for_parser(Parser::pure('n')));
+    let check = command(
+        "check",
+        Some("Check a file"),
+        Info::default().for_parser(Parser::pure('c')),
+    );
+    let parser = Info::default().for_parser(construct!([remove, rename, check]));
+
+    let help = parser
+        .clone()
+        .run_inner(Args::from(&["--help"]))
+        .unwrap_err()
+        .unwrap_stdout();
+    let expected_help = "\
+Usage: COMMAND ...
+
+Available options:
+    -h, --help   Prints help information
+
+Available commands:
+    remove, rm  Remove a file
+    rename      Rename a file
+    check       Check a file
+";
+    assert_eq!(expected_help, help);
+
+    for (input, expected) in [
+        ("remove", 'r'),
+        ("rm", 'r'),
+        ("delete", 'r'),
+        ("rem", 'r'),
+        ("ren", 'n'),
+        ("check", 'c'),
+    ] {
+        let res = parser.clone().run_inner(Args::from(&[input])).unwrap();
+        assert_eq!(res, expected, "{}", input);
+    }
+
+    // ambiguous prefix, hidden alias prefix and prefix of a command without allow_prefix
+    for input in ["re", "del", "che"] {
+        parser
+            .clone()
+            .run_inner(Args::from(&[input]))
+            .unwrap_err()
+            .unwrap_stderr();
+    }
+
+    // prefix shared with a hidden alias of another command is ambiguous too
+    let status = Command::new("status")
+        .allow_prefix()
+        .for_parser(Info::default().for_parser(Parser::pure('s')));
+    let save = Command::new("save")
+        .hidden_alias("stash")
+        .for_parser(Info::default().for_parser(Parser::pure('h')));
+    let parser = Info::default().for_parser(construct!([status, save]));
+    for (input, expected) in [("stat", 's'), ("stash", 'h')] {
+        let res = parser.clone().run_inner(Args::from(&[input])).unwrap();
+        assert_eq!(res, expected, "{}", input);
+    }
+    parser
+        .run_inner(Args::from(&["st"]))
+        .unwrap_err()
+        .unwrap_stderr();
+}
+
+#[test]
+fn default_command() {
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum Cmd {
+        Status(bool),
+        Add(String),
+    }
+    let short = short('s').long("short").switch();
+    let status = Command::new("status")
+        .help("Show the status")
+        .default_command()
+        .for_parser(Info::default().descr("Status").for_parser(short))
+        .map(Cmd::Status);
+    let add = command(
+        "add",
+        Some("Add a file"),
+        Info::default().for_parser(positional("FILE")),
+    )
+    .map(Cmd::Add);
+    let parser = Info::default().for_parser(construct!([add, status]));
+
+    let help = parser
+        .clone()
+        .run_inner(Args::from(&["--help"]))
+        .unwrap_err()
+        .unwrap_stdout();
+    let expected_help = "\
+Usage: [COMMAND]
+
+Available options:
+    -h, --help   Prints help information
+
+Available commands:
+    add     Add a file
+    status  Show the status
+";
+    assert_eq!(expected_help, help);
+
+    let help = parser
+        .clone()
+        .run_inner(Args::from(&["status", "--help"]))
+        .unwrap_err()
+        .unwrap_stdout();
+    let expected_help = "\
+Status
+
+Usage: [-s]
+
+Available options:
+    -s, --short
+    -h, --help    Prints help information
+";
+    assert_eq!(expected_help, help);
+
+    for (input, expected) in [
+        (&[][..], Cmd::Status(false)),
+        (&["-s"], Cmd::Status(true)),
+        (&["status"], Cmd::Status(false)),
+        (&["status", "--short"], Cmd::Status(true)),
+        (&["add", "foo"], Cmd::Add("foo".to_owned())),
+    ] {
+        let res = parser.clone().run_inner(Args::from(input)).unwrap();
+        assert_eq!(res, expected, "{:?}", input);
+    }
+
+    let err = parser
+        .run_inner(Args::from(&["-x"]))
+        .unwrap_err()
+        .unwrap_stderr();
+    assert_eq!(err, "-x is not expected in this context");
+}
+
+#[test]
+fn default_command_with_top_level_flags() {
+    let brief = short('s').switch();
+    let status = Command::new("status")
+        .default_command()
+        .for_parser(Info::default().for_parser(brief));
+    let verbose = short('v').switch();
+    let parser = Info::default().for_parser(construct!(status, verbose));
+
+    let help = parser
+        .clone()
+        .run_inner(Args::from(&["--help"]))
+        .unwrap_err()
+        .unwrap_stdout();
+    assert!(help.starts_with("Usage: [COMMAND] [-v]\n"), "{}", help);
+
+    for (input, expected) in [
+        (&[][..], (false, false)),
+        (&["-v"], (false, true)),
+        (&["-v", "-s"], (true, true)),
+        (&["-s", "-v"], (true, true)),
+        (&["status", "-s"], (true, false)),
+    ] {
+        let res = parser.clone().run_inner(Args::from(input)).unwrap();
+        assert_eq!(res, expected, "{:?}", input);
+    }
+
+    let err = parser
+        .run_inner(Args::from(&["-v", "-x"]))
+        .unwrap_err()
+        .unwrap_stderr();
+    assert_eq!(err, "-x is not expected in this context");
+}
+
+#[test]
+fn default_command_with_other_commands() {
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum Cmd {
+        Status(Option<String>),
+        Other,
+    }
+    let status = || {
+        let path = positional("PATH").optional().map(Cmd::Status);
+        Command::new("status")
+            .default_command()
+            .for_parser(Info::default().for_parser(path))
+    };
+    let other = || {
+        Command::new("other")
+            .alias("o")
+            .for_parser(Info::default().for_parser(Parser::pure(Cmd::Other)))
+    };
+    let status_first = {
+        let (status, other) = (status(), other());
+        construct!([status, other])
+    };
+    let other_first = {
+        let (other, status) = (other(), status());
+        construct!([other, status])
+    };
+    let parsers = [
+        Info::default().for_parser(status_first),
+        Info::default().for_parser(other_first),
+    ];
+
+    for parser in parsers {
+        for (input, expected) in [
+            (&[][..], Cmd::Status(None)),
+            (&["src"], Cmd::Status(Some("src".to_owned()))),
+            (&["status", "other"], Cmd::Status(Some("other".to_owned()))),
+            (&["other"], Cmd::Other),
+            (&["o"], Cmd::Other),
+        ] {
+            let res = parser.clone().run_inner(Args::from(input)).unwrap();
+            assert_eq!(res, expected, "{:?}", input);
+        }
+    }
+}
+
+#[test]
+fn external_commands() {
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum Cmd {
+        Check,
+        External(String, Vec<std::ffi::OsString>),
+    }
+    let check = command(
+        "check",
+        Some("Check a package"),
+        Info::default().for_parser(Parser::pure(Cmd::Check)),
+    );
+    let external = external_command().map(|(name, args)| Cmd::External(name, args));
+    let verbose = short('v').switch();
+    let cmd = construct!([check, external]);
+    let parser = Info::default().for_parser(construct!(verbose, cmd));
+
+    let help = parser
+        .clone()
+        .run_inner(Args::from(&["--help"]))
+        .unwrap_err()
+        .unwrap_stdout();
+    let expected_help = "\
+Usage: [-v] COMMAND ...
+
+Available options:
+    -v
+    -h, --help   Prints help information
+
+Available commands:
+    check        Check a package
+    COMMAND ...  External command
+";
+    assert_eq!(expected_help, help);
+
+    let res = parser.clone().run_inner(Args::from(&["check"])).unwrap();
+    assert_eq!(res, (false, Cmd::Check));
+
+    let res = parser
+        .clone()
+        .run_inner(Args::from(&["-v", "foo", "-ab", "--x=1", "--", "-v"]))
+        .unwrap();
+    let rest = ["-ab", "--x=1", "--", "-v"]
+        .iter()
+        .map(Into::into)
+        .collect();
+    assert_eq!(res, (true, Cmd::External("foo".to_owned(), rest)));
+
+    let err = parser
+        .run_inner(Args::from(&["chek"]))
+        .unwrap_err()
+        .unwrap_stderr();
+    assert_eq!(err, "No such command: `chek`, did you mean `check`?");
+}
+
+#[test]
+fn describe_parser() {
+    use crate::introspect::*;
+    let verbose = short('v')
+        .long("verbose")
+        .long("loud")
+        .help("Print more")
+        .switch()
+        .many();
+    let debug = long("debug").switch().hide();
+    let name = long("name").argument("NAME").optional();
+    let file = Positional::new("FILE").help("File to add").string();
+    let add = Command::new("add")
+        .help("Add a file")
+        .alias("a")
+        .hidden_alias("plus")
+        .for_parser(Info::default().descr("Adds a file").for_parser(file));
+    let parser = Info::default()
+        .version("1.0")
+        .for_parser(construct!(verbose, debug, name, add));
+
+    let doc = parser.describe();
+    assert_eq!(doc.version.as_deref(), Some("1.0"));
+    assert_eq!(doc.descr, None);
+
+    let entries = doc.items.entries();
+    assert_eq!(entries.len(), 4);
+
+    let ItemDoc::Option(opt) = entries[0].item else {
+        panic!("{:?}", entries[0]);
+    };
+    assert_eq!(opt.short, Some('v'));
+    assert_eq!(opt.long, Some("verbose"));
+    assert_eq!(opt.long_aliases, vec!["loud"]);
+    assert_eq!(opt.metavar, None);
+    assert_eq!(opt.help.as_deref(), Some("Print more"));
+    assert_eq!(entries[0].occurrence, Occurrence::Many);
+    assert!(!entries[0].hidden);
+
+    assert!(entries[1].hidden);
+    assert_eq!(entries[1].occurrence, Occurrence::Optional);
+
+    let ItemDoc::Option(opt) = entries[2].item else {
+        panic!("{:?}", entries[2]);
+    };
+    assert_eq!(opt.metavar, Some("NAME"));
+    assert_eq!(entries[2].occurrence, Occurrence::Optional);
+
+    let ItemDoc::Command(cmd) = entries[3].item else {
+        panic!("{:?}", entries[3]);
+    };
+    assert_eq!(cmd.name, "add");
+    assert_eq!(cmd.aliases, vec!["a"]);
+    assert_eq!(cmd.hidden_aliases, vec!["plus"]);
+    assert_eq!(cmd.help.as_deref(), Some("Add a file"));
+    assert_eq!(cmd.parser.descr.as_deref(), Some("Adds a file"));
+    let inner = cmd.parser.items.entries();
+    assert_eq!(inner.len(), 1);
+    assert_eq!(
+        inner[0].item,
+        &ItemDoc::Positional(PositionalDoc {
+            metavar: "FILE",
+            help: Some("File to add".to_owned()),
+            default: None,
+            section: None,
+        })
+    );
+
+    let help_entries = doc.help_items.entries();
+    assert_eq!(help_entries.len(), 2);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn describe_json() {
+    let verbose = short('v').long("verbose").help("Print \"more\"").switch();
+    let file = positional("FILE");
+    let add = command(
+        "add",
+        Some("Add a file"),
+        Info::default().descr("Adds a file").for_parser(file),
+    );
+    let parser = Info::default()
+        .version("1.0")
+        .for_parser(construct!(verbose, add));
+
+    let expected = r#"{
+  "descr": null,
+  "header": null,
+  "footer": null,
+  "usage": null,
+  "version": "1.0",
+  "sections": [],
+  "items": {
+    "all": [
+      {
+        "optional": {
+          "kind": "option",
+          "short": "v",
+          "long": "verbose",
+          "short_aliases": [],
+          "long_aliases": [],
+          "metavar": null,
+          "help": "Print \"more\"",
+          "default": null,
+          "section": null
+        }
+      },
+      {
+        "kind": "command",
+        "name": "add",
+        "aliases": [],
+        "hidden_aliases": [],
+        "help": "Add a file",
+        "section": null,
+        "parser": {
+          "descr": "Adds a file",
+          "header": null,
+          "footer": null,
+          "usage": null,
+          "version": null,
+          "sections": [],
+          "items": {
+            "kind": "positional",
+            "metavar": "FILE",
+            "help": null,
+            "default": null,
+            "section": null
+          },
+          "help_items": {
+            "kind": "option",
+            "short": "h",
+            "long": "help",
+            "short_aliases": [],
+            "long_aliases": [],
+            "metavar": null,
+            "help": "Prints help information",
+            "default": null,
+            "section": null
+          }
+        }
+      }
+    ]
+  },
+  "help_items": {
+    "any": [
+      {
+        "kind": "option",
+        "short": "h",
+        "long": "help",
+        "short_aliases": [],
+        "long_aliases": [],
+        "metavar": null,
+        "help": "Prints help information",
+        "default": null,
+        "section": null
+      },
+      {
+        "kind": "option",
+        "short": "v",
+        "long": "version",
+        "short_aliases": [],
+        "long_aliases": [],
+        "metavar": null,
+        "help": "Prints version information",
+        "default": null,
+        "section": null
+      }
+    ]
+  }
+}
+"#;
+    let json = parser.to_json();
+    assert_eq!(expected, json);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_round_trip() {
+    use crate::introspect::{IncompatibilityKind as K, ParserDoc};
+
+    fn old() -> OptionParser<()> {
+        let verbose = short('v')
+            .short('V')
+            .long("verbose")
+            .long("loud")
+            .help("Print \"more\"\n\tand more \u{1}")
+            .switch();
+        let jobs = long("jobs")
+            .argument("JOBS")
+            .fallback_display("1".to_owned())
+            .help_section("Performance");
+        let files = positional("FILE").many().group_help("Inputs");
+        let inner = construct!(verbose, jobs, files).map(|_| ());
+        let add = Command::new("add")
+            .help("Add a file")
+            .alias("a")
+            .hidden_alias("plus")
+            .for_parser(Info::default().descr("Adds ✓").for_parser(inner));
+        let ext = external_command().hide().map(|_| ());
+        Info::default()
+            .version("1.0")
+            .header("header")
+            .footer("footer")
+            .usage("usage")
+            .sections(&["Performance"])
+            .for_parser(construct!([add, ext]))
+    }
+
+    fn new() -> OptionParser<()> {
+        let verbose = long("verbose").switch();
+        let add = Command::new("add").for_parser(Info::default().for_parser(verbose));
+        Info::default().for_parser(add.map(|_| ()))
+    }
+
+    let saved = old().to_json();
+    let loaded = ParserDoc::from_json(&saved).unwrap();
+    assert_eq!(loaded, old().describe());
+    assert_eq!(loaded.to_json(), saved);
+
+    let kinds = loaded
+        .breaking_changes(&new().describe())
+        .into_iter()
+        .map(|c| c.kind)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            K::RemovedName("a".to_owned()),
+            K::RemovedName("plus".to_owned()),
+            K::RemovedName("--loud".to_owned()),
+            K::RemovedName("-v".to_owned()),
+            K::RemovedName("-V".to_owned()),
+            K::RemovedName("--jobs".to_owned()),
+            K::RemovedPositional("FILE"),
+        ]
+    );
+
+    assert!(ParserDoc::from_json("").is_err());
+    assert!(ParserDoc::from_json("{}").is_err());
+    assert!(ParserDoc::from_json(&format!("{}}}", saved)).is_err());
+    assert!(ParserDoc::from_json(&saved.replace("\"option\"", "\"flag\"")).is_err());
+}
+
+#[test]
+fn breaking_changes() {
+    use crate::introspect::IncompatibilityKind as K;
+
+    fn old() -> OptionParser<()> {
+        let verbose = short('v').long("verbose").switch();
+        let name = long("name").argument("NAME").optional();
+        let jobs = long("jobs").switch().many();
+        let file = positional("FILE").many();
+        let inner = construct!(verbose, name, jobs, file).map(|_| ());
+        let add = Command::new("add")
+            .alias("a")
+            .for_parser(Info::default().for_parser(inner));
+        let rm = Command::new("rm").for_parser(Info::default().for_parser(Parser::pure(())));
+        Info::default().for_parser(construct!([add, rm]))
+    }
+
+    fn new() -> OptionParser<()> {
+        let verbose = long("verbose").switch();
+        let name = long("name").switch();
+        let jobs = long("jobs").switch();
+        let file = positional("FILE");
+        let target = positional("TARGET");
+        let inner = construct!(verbose, name, jobs, file, target).map(|_| ());
+        let add = Command::new("add").for_parser(Info::default().for_parser(inner));
+        Info::default().for_parser(add)
+    }
+
+    let changes = old().describe().breaking_changes(&new().describe());
+    let kinds = changes.iter().map(|c| c.kind.clone()).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            K::RemovedName("a".to_owned()),
+            K::RemovedCommand("rm"),
+            K::RemovedName("-v".to_owned()),
+            K::ArityChanged("--name".to_owned(), false),
+            K::NoLongerRepeatable("--jobs".to_owned()),
+            K::NoLongerRepeatable("FILE".to_owned()),
+            K::BecameRequired("FILE".to_owned()),
+            K::AddedRequired("TARGET".to_owned()),
+        ]
+    );
+    assert_eq!(changes[2].command, vec!["add"]);
+    assert_eq!(changes[2].to_string(), "add: `-v` is no longer accepted");
+
+    assert!(old()
+        .describe()
+        .breaking_changes(&old().describe())
+        .is_empty());
+    let changes = new().describe().breaking_changes(&old().describe());
+    let kinds = changes.into_iter().map(|c| c.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            K::ArityChanged("--name".to_owned(), true),
+            K::RemovedPositional("TARGET"),
+        ]
+    );
+}
+
+#[test]
+fn render_markdown() {
+    let file = Positional::new("FILE").help("File to add").string();
+    let add = Command::new("add")
+        .help("Add a file")
+        .alias("a")
+        .for_parser(Info::default().descr("Adds a file").for_parser(file));
+    let verbose = short('v').long("verbose").help("Be verbose").switch();
+    let parser = Info::default()
+        .descr("Manages files")
+        .footer("See also: rm")
+        .for_parser(construct!(verbose, add));
+
+    let expected = "\
+# app
+
+Manages files
+
+```text
+app [-v] COMMAND ...
+```
+
+### Options
+
+| Name | Description |
+| --- | --- |
+| `-v, --verbose` | Be verbose |
+| `-h, --help` | Prints help information |
+
+### Commands
+
+| Name | Description |
+| --- | --- |
+| [`add`](#app-add), `a` | Add a file |
+
+See also: rm
+
+<a id=\"app-add\"></a>
+
+## app add
+
+Adds a file
+
+```text
+app add <FILE>
+```
+
+### Positional items
+
+| Name | Description |
+| --- | --- |
+| `<FILE>` | File to add |
+
+### Options
+
+| Name | Description |
+| --- | --- |
+| `-h, --help` | Prints help information |
+";
+    assert_eq!(expected, parser.render_markdown("app"));
+
+    // name doesn't have to be static
+    let name = String::from("app");
+    let html = parser.render_html(&name);
+    assert!(html.starts_with("<h1 id=\"app\">app</h1>\n<p>Manages files</p>\n"));
+    assert!(html.contains("<a href=\"#app-add\"><code>add</code></a>, <code>a</code>"));
+    assert!(html.contains("<h2 id=\"app-add\">app add</h2>"));
+    assert!(html.contains("<tr><td><code>&lt;FILE&gt;</code></td><td>File to add</td></tr>"));
+}
+
+#[test]
+fn fallback_display_help() {
+    let port = short('p')
+        .long("port")
+        .help("Port to listen on")
+        .argument("PORT")
+        .from_str::<u16>()
+        .fallback_display(8080);
+    let host = long("host")
+        .argument("HOST")
+        .fallback_display("localhost".to_owned());
+    let dir = positional("DIR").fallback_display(".".to_owned());
+    let parser = Info::default().for_parser(construct!(port, host, dir));
+
+    let help = parser
+        .clone()
+        .run_inner(Args::from(&["--help"]))
+        .unwrap_err()
+        .unwrap_stdout();
+    let expected_help = "\
+Usage: [-p PORT] [--host HOST] [<DIR>]
+
+Available positional items:
+    <DIR>  [default: .]
+
+Available options:
+    -p, --port <PORT>  Port to listen on [default: 8080]
+        --host <HOST>  [default: localhost]
+    -h, --help         Prints help information
+";
+    assert_eq!(expected_help, help);
+
+    let doc = parser.describe();
+    let entries = doc.items.entries();
+    let crate::introspect::ItemDoc::Option(opt) = entries[0].item else {
+        panic!("{:?}", entries[0]);
+    };
+    assert_eq!(opt.default.as_deref(), Some("8080"));
+
+    let res = parser.run_inner(Args::from(&[])).unwrap();
+    assert_eq!(res, (8080, "localhost".to_owned(), ".".to_owned()));
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn testing_helpers() {
+    use crate::testing::*;
+    let file = positional("FILE");
+    let add = command("add", Some("Add a file"), Info::default().for_parser(file));
+    let parser = Info::default().for_parser(add);
+
+    check_table(
+        &parser,
+        &[
+            (&["add", "foo"], "\"foo\""),
+            (
+                &["add"],
+                "stderr: Expected <FILE>, pass --help for usage information",
+            ),
+        ],
+    );
+
+    let help = all_help_text(&parser, "app");
+    let expected = "\
+==> app <==
+Usage: COMMAND ...
+
+Available options:
+    -h, --help   Prints help information
+
+Available commands:
+    add  Add a file
+
+==> app add <==
+Usage: <FILE>
+
+Available positional items:
+    <FILE>
+
+Available options:
+    -h, --help   Prints help information
+";
+    assert_eq!(expected, help);
+
+    // snapshot is compared rather than written even if tests run with BPAF_BLESS set
+    std::env::remove_var(BLESS_VAR);
+    let path = std::env::temp_dir().join(format!("bpaf-snapshot-{}.txt", std::process::id()));
+    std::fs::write(&path, &help).unwrap();
+    assert_snapshot(&path, &help);
+    let mismatch = std::panic::catch_unwind(|| assert_snapshot(&path, "something else"));
+    std::fs::remove_file(&path).unwrap();
+    assert!(mismatch.is_err());
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn fuzz_harness() {
+    use crate::testing::*;
+    let name = long("name").argument("NAME");
+    let count = long("count").argument("N").from_str::<u32>().many();
+    let file = positional("FILE").optional();
+    let add = command(
+        "add",
+        Some("Add"),
+        Info::default().for_parser(construct!(name, count, file)),
+    );
+    let quiet = long("quiet").switch();
+    let parser = Info::default().for_parser(construct!(quiet, add));
+    // values that fail to parse are reported
+    let bad_values = Fuzz::new(&parser).values(|_| String::from("x"));
+    let failure = bad_values.run().unwrap_err();
+    assert!(
+        matches!(failure.kind, FuzzFailureKind::Rejected(_)),
+        "{}",
+        failure
+    );
+
+    // short flags glued together with `=` are reported as errors
+    let a = short('a').switch();
+    let b = short('b').switch();
+    let parser = Info::default().for_parser(construct!(a, b));
+    Fuzz::new(&parser).check();
+    Fuzz::new(&parser).seed(42).iterations(100).check();
+
+    // values parsed from valid lines are turned back into arguments and parsed again
+    #[derive(Debug, Clone, PartialEq)]
+    struct Opts {
+        quiet: bool,
+        name: String,
+        lossy: bool,
+    }
+    impl Unparse for Opts {
+        fn unparse(&self, args: &mut UnparseArgs) {
+            if self.quiet {
+                args.flag("--quiet");
+            }
+            args.named("--name", self.name.clone().into());
+        }
+    }
+    let quiet = long("quiet").switch();
+    let name = long("name").argument("NAME");
+    let lossy = Parser::pure(false);
+    let parser = Info::default().for_parser(construct!(Opts { quiet, name, lossy }));
+    Fuzz::new(&parser).round_trip().check();
+
+    let quiet = long("quiet").switch();
+    let name = long("name").argument("NAME");
+    let lossy = long("lossy").switch();
+    let parser = Info::default().for_parser(construct!(Opts { quiet, name, lossy }));
+    let failure = Fuzz::new(&parser).round_trip().run().unwrap_err();
+    assert!(
+        matches!(failure.kind, FuzzFailureKind::RoundTrip(_)),
+        "{}",
+        failure
+    );
+}
+
+#[test]
+fn many_with_non_failing_parser() {
+    let verbose = short('v').switch().many();
+    let parser = Info::default().for_parser(verbose);
+    let err = parser
+        .run_inner(Args::from(&["-v"]))
+        .unwrap_err()
+        .unwrap_stderr();
+    let expected =
+        "many can't be used with a parser that succeeds without consuming anything: [-v]";
+    assert_eq!(expected, err);
+
+    let file = positional("FILE").optional().many();
+    let cmd = command("add", Some("Add"), Info::default().for_parser(file));
+    let err = Info::default().try_for_parser(cmd).err().unwrap();
+    let expected =
+        "many can't be used with a parser that succeeds without consuming anything: [<FILE>]";
+    assert_eq!(expected, err);
+
+    let files = positional("FILE").many();
+    let parser = Info::default().try_for_parser(files).ok().unwrap();
+    let r = parser.run_inner(Args::from(&["a", "b"])).unwrap();
+    assert_eq!(r, vec!["a", "b"]);
+}
+
+#[test]
+fn short_flags_with_value_and_many_names() {
+    let a = short('a').switch();
+    let b = short('b').argument("B");
+    let parser = Info::default().for_parser(construct!(a, b));
+    let err = parser
+        .run_inner(Args::from(&["-ab=1"]))
+        .unwrap_err()
+        .unwrap_stderr();
+    let expected =
+        "-ab=1 is not a valid argument, short flag with a value must have exactly one name";
+    assert_eq!(expected, err);
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn parser_shared_between_threads() {
+    use std::sync::OnceLock;
+    static PARSER: OnceLock<OptionParser<(bool, u32)>> = OnceLock::new();
+    let parser = PARSER.get_or_init(|| {
+        let verbose = short('v').switch();
+        let n = long("n").argument("N").from_str::<u32>().fallback(1);
+        Info::default().for_parser(construct!(verbose, n))
+    });
+    let handles = (0..4u32)
+        .map(|i| {
+            std::thread::spawn(move || {
+                let n = i.to_string();
+                let args = Args::from(&["--n", n.as_str()]);
+                parser.clone().run_inner(args).unwrap()
+            })
+        })
+        .collect::<Vec<_>>();
+    for (i, h) in handles.into_iter().enumerate() {
+        assert_eq!(h.join().unwrap(), (false, i as u32));
+    }
+}
+
+#[test]
+fn run_line_reuses_parser() {
+    #[derive(Debug, Clone, PartialEq)]
+    enum Cmd {
+        Add(String, bool),
+        Remove(Vec<String>),
+    }
+    let name = positional("NAME");
+    let force = short('f').long("force").switch();
+    let add = command(
+        "add",
+        Some("Add a name"),
+        Info::default().for_parser(construct!(Cmd::Add(name, force))),
+    );
+    let names = positional("NAME").many();
+    let remove = command(
+        "rm",
+        Some("Remove names"),
+        Info::default().for_parser(construct!(Cmd::Remove(names))),
+    );
+    let parser = Info::default()
+        .version("1.0")
+        .for_parser(construct!([add, remove]));
+
+    let r = parser.run_line(r#"add "Jane Doe" -f"#).unwrap();
+    assert_eq!(r, Cmd::Add("Jane Doe".to_owned(), true));
+    let r = parser.run_line(r"rm a\ b 'c d'  ").unwrap();
+    assert_eq!(r, Cmd::Remove(vec!["a b".to_owned(), "c d".to_owned()]));
+
+    let version = parser.run_line("--version").unwrap_err().unwrap_stdout();
+    assert_eq!(version, "Version: 1.0");
+    let help = parser.run_line("rm --help").unwrap_err().unwrap_stdout();
+    assert!(help.contains("<NAME>"), "{}", help);
+
+    let err = parser.run_line("add").unwrap_err().unwrap_stderr();
+    assert!(err.starts_with("Expected"), "{}", err);
+    let err = parser.run_line("add 'Jane").unwrap_err().unwrap_stderr();
+    assert_eq!(
+        err,
+        "unterminated single quote starting at line 1, column 5"
+    );
+
+    let r = parser.run_line("add x").unwrap();
+    assert_eq!(r, Cmd::Add("x".to_owned(), false));
+}
+
+#[test]
+fn multicall_dispatch() {
+    #[derive(Debug, Clone, PartialEq)]
+    enum Cmd {
+        Fmt(bool, String),
+        Check(String),
+    }
+    let verbose = short('v').switch();
+    let file = positional("FILE");
+    let fmt = Info::default().for_parser(construct!(Cmd::Fmt(verbose, file)));
+    let fmt = command("fmt", Some("Format a file"), fmt);
+    let file = positional("FILE");
+    let check = Info::default().for_parser(construct!(Cmd::Check(file)));
+    let check = Command::new("check")
+        .help("Check a file")
+        .hidden_alias("lint")
+        .for_parser(check);
+    let parser = Info::default().for_parser(construct!([fmt, check]));
+
+    let run = |argv0: &str, args: &[&str]| {
+        let args = Args::from(args).multicall(argv0);
+        parser.clone().run_inner(args)
+    };
+
+    let expected = Cmd::Fmt(true, "a.rs".to_owned());
+    assert_eq!(
+        run("/usr/bin/mytool-fmt", &["-v", "a.rs"]).unwrap(),
+        expected
+    );
+    assert_eq!(run("fmt", &["-v", "a.rs"]).unwrap(), expected);
+    assert_eq!(run("mytool", &["fmt", "-v", "a.rs"]).unwrap(), expected);
+    assert_eq!(
+        run("./mytool-lint", &["a.rs"]).unwrap(),
+        Cmd::Check("a.rs".to_owned())
+    );
+
+    // only the top level command is selected by the program name
+    let err = run("mytool-fmt", &["fmt", "a.rs"])
+        .unwrap_err()
+        .unwrap_stderr();
+    assert_eq!(err, "a.rs is not expected in this context");
+    // no partial matches
+    let err = run("mytoolfmt", &["a.rs"]).unwrap_err().unwrap_stderr();
+    assert!(err.starts_with("Expected"), "{}", err);
+
+    let help = run("/usr/bin/mytool-fmt", &["--help"])
+        .unwrap_err()
+        .unwrap_stdout();
+    assert!(
+        help.starts_with("Usage: mytool-fmt [-v] <FILE>\n"),
+        "{}",
+        help
+    );
+    let help = run("/usr/bin/mytool", &["fmt", "--help"])
+        .unwrap_err()
+        .unwrap_stdout();
+    assert!(
+        help.starts_with("Usage: mytool fmt [-v] <FILE>\n"),
+        "{}",
+        help
+    );
+    let help = run("/usr/bin/mytool", &["--help"])
+        .unwrap_err()
+        .unwrap_stdout();
+    assert!(help.starts_with("Usage: mytool COMMAND ...\n"), "{}", help);
+
+    let help = parser
+        .run_inner(Args::from(&["--help"]))
+        .unwrap_err()
+        .unwrap_stdout();
+    assert!(help.starts_with("Usage: COMMAND ...\n"), "{}", help);
+}
+
+#[test]
+fn configurable_help_and_version() {
+    let host = short('h').long("host").argument("HOST");
+    let verbose = short('v').long("verbose").switch();
+    let parser = Info::default()
+        .version("1.2")
+        .help_flag(None, Some("help"))
+        .version_flag(Some('V'), Some("version"))
+        .version_format("tool {version}")
+        .for_parser(construct!(host, verbose));
+
+    let r = parser.run_line("-h example.com -v").unwrap();
+    assert_eq!(r, ("example.com".to_owned(), true));
+    let version = parser.run_line("-V").unwrap_err().unwrap_stdout();
+    assert_eq!(version, "tool 1.2");
+
+    let help = parser.run_line("--help").unwrap_err().unwrap_stdout();
+    let expected = "\
+Usage: -h HOST [-v]
+
+Available options:
+    -h, --host <HOST>
+    -v, --verbose
+        --help         Prints help information
+    -V, --version      Prints version information
+";
+    assert_eq!(expected, help);
+
+    // without help and version flags
+    let verbose = short('v').switch();
+    let parser = Info::default()
+        .version("1.2")
+        .help_flag(None, None)
+        .version_flag(None, None)
+        .for_parser(verbose);
+    let err = parser.run_line("--help").unwrap_err().unwrap_stderr();
+    assert_eq!(err, "--help is not expected in this context");
+    let err = parser.run_line("--version").unwrap_err().unwrap_stderr();
+    assert_eq!(err, "--version is not expected in this context");
+}
+
+#[test]
+fn help_command() {
+    let dry_run = long("dry-run").switch();
+    let remote = Info::default()
+        .descr("Manage remotes")
+        .for_parser(positional("NAME"));
+    let remote = Command::new("remote")
+        .help("Manage remotes")
+        .alias("r")
+        .for_parser(remote);
+    let fetch = Info::default()
+        .descr("Fetch from a remote")
+        .for_parser(construct!(dry_run, remote));
+    let fetch = command("fetch", Some("Fetch from a remote"), fetch);
+    let parser = Info::default().help_command().for_parser(fetch);
+
+    let run = |line: &str| {
+        let args = Args::from_shell_str(line).unwrap().name("git");
+        parser.clone().run_inner(args).unwrap_err()
+    };
+
+    let help = run("help").unwrap_stdout();
+    let expected = "\
+Usage: git COMMAND ...
+
+Available options:
+    -h, --help   Prints help information
+
+Available commands:
+    fetch  Fetch from a remote
+    help   Prints help information for a command
+";
+    assert_eq!(expected, help);
+
+    let help = run("help fetch r").unwrap_stdout();
+    assert_eq!(help, run("fetch --dry-run remote --help").unwrap_stdout());
+    assert!(
+        help.starts_with("Manage remotes\n\nUsage: git fetch remote <NAME>\n"),
+        "{}",
+        help
+    );
+
+    let err = run("help fetch push").unwrap_stderr();
+    assert_eq!(err, "push is not a known command");
+
+    let r = parser.run_line("fetch --dry-run r origin").unwrap();
+    assert_eq!(r, (true, "origin".to_owned()));
+}
+
+#[test]
+fn help_command_as_a_value() {
+    let topic = positional("TOPIC");
+    let parser = Info::default().help_command().for_parser(topic);
+
+    let r = parser.run_line("help").unwrap();
+    assert_eq!(r, "help");
+
+    let help = parser.run_line("").unwrap_err().unwrap_stderr();
+    assert_eq!(help, "Expected <TOPIC>, pass --help for usage information");
+}
+
+#[test]
+fn run_with_custom_io() {
+    let name = long("name").argument("NAME");
+    let parser = Info::default().version("1.0").for_parser(name);
+    let run = |args: &[&str]| {
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let r = parser.run_with(args, &mut stdout, &mut stderr);
+        let stdout = String::from_utf8(stdout).unwrap();
+        let stderr = String::from_utf8(stderr).unwrap();
+        (r, stdout, stderr)
+    };
+
+    let r = run(&["--name", "Bob"]);
+    assert_eq!(
+        r,
+        (
+            RunOutcome::Parsed("Bob".to_owned()),
+            String::new(),
+            String::new()
+        )
+    );
+
+    let r = run(&["--version"]);
+    assert_eq!(
+        r,
+        (
+            RunOutcome::Exit(0),
+            "Version: 1.0\n".to_owned(),
+            String::new()
+        )
+    );
+
+    let (r, stdout, stderr) = run(&["--help"]);
+    assert_eq!(r, RunOutcome::Exit(0));
+    assert!(stdout.starts_with("Usage: --name NAME\n"), "{}", stdout);
+    assert_eq!(stderr, "");
+
+    let (r, stdout, stderr) = run(&[]);
+    assert_eq!(r, RunOutcome::Exit(1));
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "Expected --name NAME\nUsage: --name NAME\nPass --help for usage information\n"
+    );
+
+    let args = vec![String::from("--name")];
+    let parser = Info::default()
+        .error_code(2)
+        .for_parser(long("name").argument("NAME"));
+    let r = parser.run_with(args, &mut std::io::sink(), &mut std::io::sink());
+    assert_eq!(r, RunOutcome::Exit(2));
+}
+
+#[test]
+fn short_and_long_help() {
+    let jobs = short('j')
+        .long("jobs")
+        .help("Number of jobs")
+        .long_help("Number of jobs\n\nDefaults to the number of CPUs")
+        .argument("N");
+    let file = Positional::new("FILE")
+        .help("File to build")
+        .long_help("File to build, - for stdin")
+        .string();
+    let build = Info::default().for_parser(construct!(jobs, file));
+    let build = command("build", Some("Build a file"), build);
+    let parser = Info::default().help_command().for_parser(build);
+
+    let short_help = parser.run_line("build -h").unwrap_err().unwrap_stdout();
+    let expected = "\
+Usage: -j N <FILE>
+
+Available positional items:
+    <FILE>  File to build
+
+Available options:
+    -j, --jobs <N>  Number of jobs
+    -h, --help      Prints help information
+";
+    assert_eq!(expected, short_help);
+
+    let long_help = parser.run_line("build --help").unwrap_err().unwrap_stdout();
+    let expected = "\
+Usage: -j N <FILE>
+
+Available positional items:
+    <FILE>  File to build, - for stdin
+
+Available options:
+    -j, --jobs <N>  Number of jobs
+
+                    Defaults to the number of CPUs
+    -h, --help      Prints help information
+";
+    assert_eq!(expected, long_help);
+    let help = parser.run_line("help build").unwrap_err().unwrap_stdout();
+    assert_eq!(long_help, help);
+
+    // help with only a short name shows the short help
+    let parser = Info::default()
+        .help_flag(Some('?'), None)
+        .for_parser(long("x").help("short").long_help("long").switch());
+    let help = parser.run_line("-?").unwrap_err().unwrap_stdout();
+    assert!(help.contains("--x   short\n"), "{}", help);
+
+    let md = parser.render_markdown("app");
+    assert!(md.contains("| `--x` | long |"), "{}", md);
+}
+
+#[test]
+fn help_sections() {
+    let verbose = short('v').long("verbose").help("Print more").switch();
+    let host = long("host").help("Server to connect to").argument("HOST");
+    let port = long("port").help("Server port").argument("PORT");
+    let net = construct!(host, port)
+        .group_help("Connection")
+        .help_section("Network options");
+    let output = long("output")
+        .help("Output file")
+        .argument("FILE")
+        .help_section("Output options");
+    let trace = long("trace").switch().help_section("Debug options").hide();
+    let opts = construct!(verbose, net, output, trace).map(|_| ());
+
+    let start = command(
+        "start",
+        Some("Start the server"),
+        Info::default().for_parser(opts),
+    );
+    let stop = Info::default().for_parser(Parser::pure(()));
+    let stop = command("stop", Some("Stop the server"), stop).help_section("Server commands");
+    let status = Info::default().for_parser(Parser::pure(()));
+    let status = command("status", Some("Show status"), status);
+    let cmd = construct!([start, stop, status]);
+    let info = Info::default().sections(&["Debug options", "Output options"]);
+    let parser = info.clone().for_parser(cmd.clone());
+
+    let help = parser.run_line("start --help").unwrap_err().unwrap_stdout();
+    let expected = "\
+Usage: [-v] --host HOST --port PORT --output FILE
+
+Available options:
+    -v, --verbose   Print more
+    -h, --help      Prints help information
+
+Network options:
+  Connection
+        --host <HOST>  Server to connect to
+        --port <PORT>  Server port
+
+Output options:
+        --output <FILE>  Output file
+";
+    assert_eq!(expected, help);
+
+    let help = parser.render_help().unwrap();
+    let expected = "\
+Usage: COMMAND ...
+
+Available options:
+    -h, --help   Prints help information
+
+Available commands:
+    start   Start the server
+    status  Show status
+
+Server commands:
+    stop  Stop the server
+";
+    assert_eq!(expected, help);
+
+    let md = parser.render_markdown("app");
+    assert!(md.contains("\n### Server commands\n\n| Name | Description |\n| --- | --- |\n| [`stop`](#app-stop) | Stop the server |\n"), "{}", md);
+    assert!(md.contains("\n### Network options\n\n| Name | Description |\n| --- | --- |\n| **Connection** | |\n| `--host <HOST>` | Server to connect to |\n"), "{}", md);
+
+    let parser = info.show_empty_sections().for_parser(cmd);
+    let help = parser.render_help().unwrap();
+    assert!(
+        help.contains("\n\nDebug options:\n\nOutput options:\n\nServer commands:\n"),
+        "{}",
+        help
+    );
+}
+
+#[test]
+fn usage_compaction() {
+    fn parser() -> Parser<()> {
+        let names = ["alpha", "beta", "gamma", "delta", "epsilon", "zeta"];
+        let flags = names
+            .iter()
+            .map(|name| long(name).switch().map(|_| ()))
+            .reduce(|a, b| construct!(a, b).map(|_| ()))
+            .unwrap();
+        let jobs = short('j').long("jobs").argument("N").optional();
+        let mode = long("fast").req_flag(()).or_else(long("slow").req_flag(()));
+        let output = short('o').argument("FILE");
+        let files = positional("SRC").many();
+        construct!(flags, jobs, mode, output, files).map(|_| ())
+    }
+    fn short_parser() -> Parser<()> {
+        let output = short('o').argument("FILE");
+        let files = positional("SRC").many();
+        construct!(output, files).map(|_| ())
+    }
+
+    let full = Info::default().for_parser(parser());
+    let help = full.render_help().unwrap();
+    assert!(
+        help.starts_with("Usage: [--alpha] [--beta] [--gamma] [--delta] [--epsilon] [--zeta] [-j N] (--fast | --slow) -o FILE <SRC>...\n"),
+        "{}",
+        help
+    );
+
+    let compact = Info::default()
+        .usage_style(UsageStyle::Compact)
+        .for_parser(parser());
+    let help = compact.render_help().unwrap();
+    assert!(
+        help.starts_with("Usage: [OPTIONS] (--fast | --slow) -o FILE <SRC>...\n"),
+        "{}",
+        help
+    );
+
+    // fits into the given width
+    let short_one = Info::default()
+        .usage_style(UsageStyle::Auto)
+        .usage_width(80)
+        .for_parser(short_parser());
+    let help = short_one.render_help().unwrap();
+    assert!(help.starts_with("Usage: -o FILE <SRC>...\n"), "{}", help);
+
+    let auto = Info::default()
+        .usage_style(UsageStyle::Auto)
+        .usage_width(40)
+        .for_parser(parser());
+    let help = auto.render_help().unwrap();
+    assert!(
+        help.starts_with("Usage: [OPTIONS] (--fast | --slow)\n       -o FILE <SRC>...\n"),
+        "{}",
+        help
+    );
+
+    // without explicit width auto style uses terminal width
+    let auto = Info::default()
+        .usage_style(UsageStyle::Auto)
+        .for_parser(parser());
+    std::env::set_var("COLUMNS", "40");
+    let narrow = auto.render_help().unwrap();
+    std::env::set_var("COLUMNS", "200");
+    let wide = auto.render_help().unwrap();
+    std::env::remove_var("COLUMNS");
+    assert!(
+        narrow.starts_with("Usage: [OPTIONS] (--fast | --slow)\n       -o FILE <SRC>...\n"),
+        "{}",
+        narrow
+    );
+    assert!(wide.starts_with("Usage: [--alpha] [--beta]"), "{}", wide);
+
+    let wrapped = Info::default().usage_width(40);
+    let cmd = command("build", Some("Build"), wrapped.for_parser(parser()));
+    let parser = Info::default().for_parser(cmd);
+    let help = parser.run_line("build --help").unwrap_err().unwrap_stdout();
+    let expected = "\
+Usage: [--alpha] [--beta] [--gamma]
+       [--delta] [--epsilon] [--zeta]
+       [-j N] (--fast | --slow) -o FILE
+       <SRC>...
+";
+    assert!(help.starts_with(expected), "{}", help);
+
+    let md = parser.render_markdown("app");
+    let expected = "\
+```text
+app build [--alpha] [--beta] [--gamma]
+          [--delta] [--epsilon] [--zeta]
+          [-j N] (--fast | --slow)
+          -o FILE <SRC>...
+```";
+    assert!(md.contains(expected), "{}", md);
+}
+
+#[test]
+fn error_context() {
+    let speed = short('s').long("speed").argument("SPEED").from_str::<u32>();
+    let file = positional("FILE");
+    let run = Info::default().for_parser(construct!(speed, file));
+    let run = command("run", Some("Run a file"), run);
+    let parser = Info::default().help_flag(Some('?'), None).for_parser(run);
+    let stderr = |args: &[&str]| {
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let r = parser.run_with(args, &mut stdout, &mut stderr);
+        assert_eq!(r, RunOutcome::Exit(1));
+        String::from_utf8(stderr).unwrap()
+    };
+
+    let expected = "\
+--speed requires an argument
+    run a.txt --speed
+              ^^^^^^^
+Usage: -s SPEED <FILE>
+Pass --help for usage information
+";
+    assert_eq!(expected, stderr(&["run", "a.txt", "--speed"]));
+
+    let expected = "\
+Couldn't parse \"fast\": invalid digit found in string
+    run -s fast a.txt
+           ^^^^
+Usage: -s SPEED <FILE>
+Pass --help for usage information
+";
+    assert_eq!(expected, stderr(&["run", "-s", "fast", "a.txt"]));
+
+    // missing items are reported in the context of the command
+    let expected = "\
+Expected <FILE>
+Usage: -s SPEED <FILE>
+Pass --help for usage information
+";
+    assert_eq!(expected, stderr(&["run", "-s", "1"]));
+
+    // help flag of the top level parser is renamed
+    let expected = "\
+-ab=1 is not a valid argument, short flag with a value must have exactly one name
+    run -ab=1
+        ^^^^^
+Usage: COMMAND ...
+Pass -? for usage information
+";
+    assert_eq!(expected, stderr(&["run", "-ab=1"]));
+
+    let expected = "\
+b.txt is not expected in this context
+    run -s 1 a.txt b.txt
+                   ^^^^^
+Usage: -s SPEED <FILE>
+Pass --help for usage information
+";
+    assert_eq!(expected, stderr(&["run", "-s", "1", "a.txt", "b.txt"]));
+
+    // tests get the message alone
+    let err = parser
+        .run_line("run a.txt --speed")
+        .unwrap_err()
+        .unwrap_stderr();
+    assert_eq!(err, "--speed requires an argument");
+    let err = parser.run_line("run -s 1").unwrap_err().unwrap_stderr();
+    assert_eq!(err, "Expected <FILE>, pass --help for usage information");
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn unparse_round_trip() {
+    // derived code refers to `::bpaf`, a separate copy of this crate in tests
+    use ::bpaf::{Args, Bpaf, Unparse};
+    use std::ffi::OsString;
+
+    #[derive(Debug, Clone, PartialEq, Bpaf)]
+    #[bpaf(options, unparse)]
+    struct Opts {
+        #[bpaf(short, argument("N"), from_str(i32))]
+        offset: i32,
+        name: String,
+        verbose: bool,
+        #[bpaf(positional("FILE"), many)]
+        files: Vec<String>,
+    }
+
+    let check = |value: Opts| {
+        let args = value.to_args();
+        let argv = args.iter().map(OsString::as_os_str).collect::<Vec<_>>();
+        let parsed = opts()
+            .run_inner(Args::from(argv.as_slice()))
+            .unwrap_or_else(|err| panic!("{:?}: {:?}", args, err));
+        assert_eq!(parsed, value);
+    };
+
+    check(Opts {
+        files: vec!["-".to_owned(), "--".to_owned(), "a.txt".to_owned()],
+        offset: -5,
+        name: "-x".to_owned(),
+        verbose: true,
+    });
+    check(Opts {
+        files: Vec::new(),
+        offset: 3,
+        name: "--name=a".to_owned(),
+        verbose: false,
+    });
+    assert_eq!(
+        Opts {
+            files: vec!["-v".to_owned()],
+            offset: -5,
+            name: "x".to_owned(),
+            verbose: true,
+        }
+        .to_args(),
+        ["-o=-5", "--name=x", "--verbose", "--", "-v"]
+    );
+
+    // positional items from a flattened structure and from the outer one share one `--`
+    #[derive(Debug, Clone, PartialEq, Bpaf)]
+    #[bpaf(unparse)]
+    struct Source {
+        force: bool,
+        #[bpaf(positional("SRC"))]
+        src: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Bpaf)]
+    #[bpaf(options, unparse)]
+    struct Copy {
+        verbose: bool,
+        #[bpaf(flatten)]
+        source: Source,
+        #[bpaf(positional("DST"))]
+        dst: String,
+    }
+
+    for (src, dst) in [("a", "b"), ("-x", "--"), ("--verbose", "-")] {
+        let value = Copy {
+            verbose: true,
+            source: Source {
+                force: true,
+                src: src.to_owned(),
+            },
+            dst: dst.to_owned(),
+        };
+        let args = value.to_args();
+        assert_eq!(args, ["--verbose", "--force", "--", src, dst]);
+        let argv = args.iter().map(OsString::as_os_str).collect::<Vec<_>>();
+        let parsed = copy()
+            .run_inner(Args::from(argv.as_slice()))
+            .unwrap_or_else(|err| panic!("{:?}: {:?}", args, err));
+        assert_eq!(parsed, value);
+    }
+
+    // items of a command go after its name
+    #[derive(Debug, Clone, PartialEq, Bpaf)]
+    #[bpaf(options, unparse)]
+    enum Cmd {
+        #[bpaf(command)]
+        Build {
+            release: bool,
+            #[bpaf(positional("FILE"))]
+            file: String,
+        },
+    }
+
+    let value = Cmd::Build {
+        release: true,
+        file: "-f".to_owned(),
+    };
+    let args = value.to_args();
+    assert_eq!(args, ["build", "--release", "--", "-f"]);
+    let argv = args.iter().map(OsString::as_os_str).collect::<Vec<_>>();
+    let parsed = cmd()
+        .run_inner(Args::from(argv.as_slice()))
+        .unwrap_or_else(|err| panic!("{:?}: {:?}", args, err));
+    assert_eq!(parsed, value);
+}
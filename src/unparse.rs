@@ -0,0 +1,144 @@
+//! Turning parsed values back into command line arguments
+use std::ffi::OsString;
+
+/// Render a value back into command line arguments
+///
+/// Implementation should produce arguments that parse back into the same value, use
+/// `#[bpaf(unparse)]` to derive it along with the parser. Named items and positional items are
+/// collected separately in [`UnparseArgs`]: named items come first with values passed as
+/// `--name=value`, positional items go after `--` so values starting with `-` are not mistaken
+/// for flags:
+///
+/// ```rust
+/// # use bpaf::*;
+/// # #[cfg(feature = "derive")] {
+/// #[derive(Debug, Clone, PartialEq, Bpaf)]
+/// #[bpaf(options, unparse)]
+/// struct Opts {
+///     verbose: bool,
+///     name: String,
+///     #[bpaf(positional("FILE"))]
+///     file: String,
+/// }
+///
+/// let opts = Opts { verbose: true, name: "Bob".to_owned(), file: "-".to_owned() };
+/// assert_eq!(opts.to_args(), ["--verbose", "--name=Bob", "--", "-"]);
+/// # }
+/// ```
+///
+/// A positional parser only accepts the first remaining item, so named items can come first
+/// only if the parser takes them before positional ones. Derive rejects structures where a
+/// named field follows a positional one, including fields from `flatten` and `external`:
+///
+/// ```rust,compile_fail
+/// # use bpaf::*;
+/// #[derive(Debug, Clone, Bpaf)]
+/// #[bpaf(unparse)]
+/// struct Source {
+///     #[bpaf(positional("SRC"))]
+///     src: String,
+/// }
+///
+/// #[derive(Debug, Clone, Bpaf)]
+/// #[bpaf(options, unparse)]
+/// struct Copy {
+///     #[bpaf(flatten)]
+///     source: Source,
+///     verbose: bool,
+/// }
+/// ```
+pub trait Unparse {
+    /// Implementation adds positional items or a command
+    ///
+    /// Used by derived implementations to check the order of fields, set it to `false` if
+    /// only named items are added
+    const POSITIONAL: bool = true;
+
+    /// Implementation adds named items, set it to `false` if only positional items or
+    /// a command are added
+    const NAMED: bool = true;
+
+    /// Add items representing `self` to `args`
+    fn unparse(&self, args: &mut UnparseArgs);
+
+    /// Arguments representing `self`
+    fn to_args(&self) -> Vec<OsString> {
+        let mut args = UnparseArgs::default();
+        self.unparse(&mut args);
+        args.into_args()
+    }
+}
+
+/// Items collected by [`Unparse`], see [`into_args`](UnparseArgs::into_args) for the order
+///
+/// ```rust
+/// # use bpaf::*;
+/// let mut args = UnparseArgs::default();
+/// args.positional("-".into());
+/// args.named("--name", "Bob".into());
+/// args.flag("-v");
+/// assert_eq!(args.into_args(), ["--name=Bob", "-v", "--", "-"]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct UnparseArgs {
+    named: Vec<OsString>,
+    positional: Vec<OsString>,
+    command: Option<(OsString, Box<UnparseArgs>)>,
+}
+
+impl UnparseArgs {
+    /// Add a flag without a value such as `--verbose`
+    pub fn flag(&mut self, name: &str) {
+        self.named.push(OsString::from(name));
+    }
+
+    /// Add a named argument with a value, joined with `=` unless value is not utf8
+    pub fn named(&mut self, name: &str, value: OsString) {
+        match value.to_str() {
+            Some(value) => self
+                .named
+                .push(OsString::from(format!("{}={}", name, value))),
+            // arguments that are not valid utf8 are always treated as values
+            None => {
+                self.named.push(OsString::from(name));
+                self.named.push(value);
+            }
+        }
+    }
+
+    /// Add a positional item
+    pub fn positional(&mut self, value: OsString) {
+        self.positional.push(value);
+    }
+
+    /// Start a command, items for it are added to the result
+    pub fn command(&mut self, name: &str) -> &mut UnparseArgs {
+        let (_, inner) = self
+            .command
+            .get_or_insert_with(|| (OsString::from(name), Box::default()));
+        inner
+    }
+
+    /// Named items, then positional items after `--`
+    ///
+    /// If there's a command it comes last, followed by its own items. Positional items before
+    /// a command can't go after `--` since it would apply to the command as well and are
+    /// written as is.
+    #[must_use]
+    pub fn into_args(self) -> Vec<OsString> {
+        let mut res = self.named;
+        match self.command {
+            Some((name, inner)) => {
+                res.extend(self.positional);
+                res.push(name);
+                res.extend(inner.into_args());
+            }
+            None if self.positional.is_empty() => {}
+            None => {
+                res.push(OsString::from("--"));
+                res.extend(self.positional);
+            }
+        }
+        res
+    }
+}